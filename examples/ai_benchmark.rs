@@ -0,0 +1,68 @@
+// compares the computer's strategies without opening a window:
+//   cargo run --release --example ai_benchmark -- [games]
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use rusty_battleship::ai::{self, Difficulty};
+use rusty_battleship::engine::{Side, STANDARD_FLEET};
+use rusty_battleship::simulation;
+
+const BOARD_LENGTH: i32 = 10;
+const DIFFICULTIES: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard];
+
+fn main() {
+    let games: usize = std::env::args()
+        .nth(1)
+        .and_then(|arg| arg.parse().ok())
+        .unwrap_or(200);
+
+    println!("shots to sink a whole fleet over {} games:", games);
+    for difficulty in DIFFICULTIES.iter() {
+        let summary = simulation::benchmark(
+            *difficulty,
+            &STANDARD_FLEET,
+            BOARD_LENGTH,
+            BOARD_LENGTH,
+            games,
+            42,
+        )
+        .expect("strategy gave up before the fleet was sunk");
+
+        println!(
+            "  {:<20} mean {:>6.2}  min {:>3}  max {:>3}",
+            difficulty.strategy(&STANDARD_FLEET).name(),
+            summary.mean_shots,
+            summary.min_shots,
+            summary.max_shots
+        );
+    }
+
+    println!("wins as first player against each opponent:");
+    let mut rng = StdRng::seed_from_u64(7);
+    for first in DIFFICULTIES.iter() {
+        for second in DIFFICULTIES.iter() {
+            let mut wins = 0;
+
+            for _ in 0..games {
+                let mut computers: Vec<ai::Computer> = [*first, *second]
+                    .iter()
+                    .map(|difficulty| {
+                        let board =
+                            ai::place_fleet(&STANDARD_FLEET, BOARD_LENGTH, BOARD_LENGTH, &mut rng)
+                                .unwrap();
+                        let seed = StdRng::from_rng(&mut rng).unwrap();
+                        ai::Computer::new(board, difficulty.strategy(&STANDARD_FLEET), seed)
+                    })
+                    .collect();
+
+                let (a, b) = computers.split_at_mut(1);
+                if simulation::duel(&mut a[0], &mut b[0]) == Some(Side::First) {
+                    wins += 1;
+                }
+            }
+
+            println!("  {:?} vs {:?}: {}/{}", first, second, wins, games);
+        }
+    }
+}
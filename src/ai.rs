@@ -0,0 +1,498 @@
+// computer opponent: fleet placement and shot selection, without any i/o.
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use super::engine::{Board, Coord, ShipType, ShotError, ShotResult, TargetBoard};
+use super::ship::Ship;
+
+// tries per ship before a fleet layout is given up on.
+const PLACEMENT_ATTEMPTS: usize = 1000;
+
+// lays out 'fleet' with the same moves and rotations a player has.
+pub fn place_fleet<R: Rng>(
+    fleet: &[ShipType],
+    width: i32,
+    height: i32,
+    rng: &mut R,
+) -> Option<Board> {
+    let mut board = Board::new(width, height);
+
+    for ship_type in fleet.iter() {
+        let is_placed = (0..PLACEMENT_ATTEMPTS).any(|_| {
+            let ship = Ship::random(*ship_type, width, height, rng);
+            board.place(ship.ship_type, ship.cells()).is_ok()
+        });
+
+        if !is_placed {
+            return None;
+        }
+    }
+
+    Some(board)
+}
+
+// picks where the computer shoots next.
+pub trait AiStrategy: Send {
+    fn name(&self) -> &'static str;
+
+    // 'None' once there is nothing left to shoot at.
+    fn next_shot(&mut self, targets: &TargetBoard, rng: &mut StdRng) -> Option<Coord>;
+
+    // called with the answer to every shot this strategy picked.
+    fn record(&mut self, _shot: Coord, _result: ShotResult) {}
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+}
+
+impl Difficulty {
+    pub fn strategy(self, fleet: &[ShipType]) -> Box<dyn AiStrategy> {
+        match self {
+            Difficulty::Easy => Box::new(RandomStrategy),
+            Difficulty::Medium => Box::new(HuntTargetStrategy::new(fleet)),
+            Difficulty::Hard => Box::new(DensityStrategy::new(fleet)),
+        }
+    }
+}
+
+fn open_cells(targets: &TargetBoard) -> Vec<Coord> {
+    let mut open: Vec<Coord> = Vec::new();
+    for y in 0..targets.height() {
+        for x in 0..targets.width() {
+            let c = Coord::new(x, y);
+            if !targets.is_shot(c) {
+                open.push(c);
+            }
+        }
+    }
+
+    open
+}
+
+fn neighbours(c: Coord) -> [Coord; 4] {
+    [
+        Coord::new(c.x, c.y - 1),
+        Coord::new(c.x + 1, c.y),
+        Coord::new(c.x, c.y + 1),
+        Coord::new(c.x - 1, c.y),
+    ]
+}
+
+// hits that don't belong to a sunk ship yet, and the ships still afloat.
+struct HitTracker {
+    afloat: Vec<ShipType>,
+    unresolved: Vec<Coord>,
+}
+
+impl HitTracker {
+    fn new(fleet: &[ShipType]) -> HitTracker {
+        HitTracker {
+            afloat: fleet.to_vec(),
+            unresolved: Vec::new(),
+        }
+    }
+
+    fn record(&mut self, shot: Coord, result: ShotResult) {
+        match result {
+            ShotResult::Miss => {}
+
+            ShotResult::Hit => self.unresolved.push(shot),
+
+            ShotResult::Sunk(ship_type) => {
+                self.unresolved.push(shot);
+
+                if let Some(i) = self.afloat.iter().position(|t| *t == ship_type) {
+                    self.afloat.remove(i);
+                }
+
+                // the sunk ship is a straight run of hits through 'shot'.
+                let size = ship_type.size() as i32;
+                for (dx, dy) in [(1, 0), (0, 1)].iter() {
+                    for start in 0..size {
+                        let cells: Vec<Coord> = (0..size)
+                            .map(|i| {
+                                Coord::new(shot.x + (i - start) * dx, shot.y + (i - start) * dy)
+                            })
+                            .collect();
+
+                        if cells.iter().all(|c| self.unresolved.contains(c)) {
+                            self.unresolved.retain(|c| !cells.contains(c));
+                            return;
+                        }
+                    }
+                }
+
+                // ships touching each other made the run ambiguous.
+                self.unresolved.retain(|c| *c != shot);
+            }
+        }
+    }
+}
+
+// shoots anywhere it hasn't shot before.
+pub struct RandomStrategy;
+
+impl AiStrategy for RandomStrategy {
+    fn name(&self) -> &'static str {
+        "random"
+    }
+
+    fn next_shot(&mut self, targets: &TargetBoard, rng: &mut StdRng) -> Option<Coord> {
+        open_cells(targets).choose(rng).copied()
+    }
+}
+
+// searches a checkerboard until it hits, then works around the hits
+// until the ship goes down.
+pub struct HuntTargetStrategy {
+    tracker: HitTracker,
+}
+
+impl HuntTargetStrategy {
+    pub fn new(fleet: &[ShipType]) -> HuntTargetStrategy {
+        HuntTargetStrategy {
+            tracker: HitTracker::new(fleet),
+        }
+    }
+
+    fn target_cells(&self, targets: &TargetBoard) -> Vec<Coord> {
+        let is_open = |c: &Coord| targets.check_shot(*c).is_ok();
+        let hits = &self.tracker.unresolved;
+
+        // two hits in a row tell us the direction, so extend the line.
+        let mut in_line: Vec<Coord> = Vec::new();
+        for hit in hits.iter() {
+            for (dx, dy) in [(1, 0), (0, 1)].iter() {
+                if !hits.contains(&Coord::new(hit.x + dx, hit.y + dy)) {
+                    continue;
+                }
+
+                let mut end = *hit;
+                while hits.contains(&end) {
+                    end = Coord::new(end.x + dx, end.y + dy);
+                }
+                let mut start = *hit;
+                while hits.contains(&start) {
+                    start = Coord::new(start.x - dx, start.y - dy);
+                }
+
+                in_line.push(end);
+                in_line.push(start);
+            }
+        }
+        in_line.retain(is_open);
+        if !in_line.is_empty() {
+            return in_line;
+        }
+
+        hits.iter()
+            .flat_map(|hit| neighbours(*hit).to_vec())
+            .filter(is_open)
+            .collect()
+    }
+}
+
+impl AiStrategy for HuntTargetStrategy {
+    fn name(&self) -> &'static str {
+        "hunt/target"
+    }
+
+    fn next_shot(&mut self, targets: &TargetBoard, rng: &mut StdRng) -> Option<Coord> {
+        let candidates = self.target_cells(targets);
+        if let Some(shot) = candidates.choose(rng) {
+            return Some(*shot);
+        }
+
+        // no ship afloat is shorter than this, so a grid this wide finds them all.
+        let parity = self
+            .tracker
+            .afloat
+            .iter()
+            .map(|t| t.size() as i32)
+            .min()
+            .unwrap_or(1);
+
+        let open = open_cells(targets);
+        let hunt: Vec<Coord> = open
+            .iter()
+            .copied()
+            .filter(|c| (c.x + c.y) % parity == 0)
+            .collect();
+
+        hunt.choose(rng).or_else(|| open.choose(rng)).copied()
+    }
+
+    fn record(&mut self, shot: Coord, result: ShotResult) {
+        self.tracker.record(shot, result);
+    }
+}
+
+// counts every legal placement of the ships still afloat and shoots
+// the cell most of them cover.
+pub struct DensityStrategy {
+    tracker: HitTracker,
+}
+
+impl DensityStrategy {
+    pub fn new(fleet: &[ShipType]) -> DensityStrategy {
+        DensityStrategy {
+            tracker: HitTracker::new(fleet),
+        }
+    }
+
+    pub fn heatmap(&self, targets: &TargetBoard) -> Vec<Vec<u32>> {
+        let width = targets.width();
+        let height = targets.height();
+        let hits = &self.tracker.unresolved;
+
+        let mut heat = vec![vec![0u32; width as usize]; height as usize];
+        let mut target_heat = vec![vec![0u32; width as usize]; height as usize];
+        let mut is_targeting = false;
+
+        for ship_type in self.tracker.afloat.iter() {
+            let size = ship_type.size() as i32;
+
+            for (dx, dy) in [(1, 0), (0, 1)].iter() {
+                for y in 0..height - dy * (size - 1) {
+                    for x in 0..width - dx * (size - 1) {
+                        let cells: Vec<Coord> = (0..size)
+                            .map(|i| Coord::new(x + i * dx, y + i * dy))
+                            .collect();
+
+                        // misses and sunk ships rule a placement out.
+                        if cells
+                            .iter()
+                            .any(|c| targets.is_shot(*c) && !hits.contains(c))
+                        {
+                            continue;
+                        }
+
+                        let covered = cells.iter().filter(|c| hits.contains(c)).count() as u32;
+
+                        for c in cells.iter().filter(|c| !targets.is_shot(**c)) {
+                            heat[c.y as usize][c.x as usize] += 1;
+
+                            // placements through known hits matter far more.
+                            if covered > 0 {
+                                is_targeting = true;
+                                target_heat[c.y as usize][c.x as usize] += covered;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        if is_targeting {
+            target_heat
+        } else {
+            heat
+        }
+    }
+}
+
+impl AiStrategy for DensityStrategy {
+    fn name(&self) -> &'static str {
+        "probability density"
+    }
+
+    fn next_shot(&mut self, targets: &TargetBoard, rng: &mut StdRng) -> Option<Coord> {
+        let heat = self.heatmap(targets);
+
+        let mut best: Vec<Coord> = Vec::new();
+        let mut best_heat = 0;
+        for c in open_cells(targets) {
+            let h = heat[c.y as usize][c.x as usize];
+            if h > best_heat {
+                best_heat = h;
+                best.clear();
+            }
+            if h == best_heat {
+                best.push(c);
+            }
+        }
+
+        best.choose(rng).copied()
+    }
+
+    fn record(&mut self, shot: Coord, result: ShotResult) {
+        self.tracker.record(shot, result);
+    }
+}
+
+pub struct Computer {
+    board: Board,
+    targets: TargetBoard,
+    strategy: Box<dyn AiStrategy>,
+    rng: StdRng,
+}
+
+impl Computer {
+    pub fn new(board: Board, strategy: Box<dyn AiStrategy>, rng: StdRng) -> Computer {
+        let targets = TargetBoard::new(board.width(), board.height());
+
+        Computer {
+            board,
+            targets,
+            strategy,
+            rng,
+        }
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn targets(&self) -> &TargetBoard {
+        &self.targets
+    }
+
+    pub fn strategy(&self) -> &dyn AiStrategy {
+        self.strategy.as_ref()
+    }
+
+    pub fn next_shot(&mut self) -> Option<Coord> {
+        self.strategy.next_shot(&self.targets, &mut self.rng)
+    }
+
+    pub fn receive_shot(&mut self, c: Coord) -> Result<ShotResult, ShotError> {
+        self.board.receive_shot(c)
+    }
+
+    pub fn record(&mut self, c: Coord, result: ShotResult) {
+        self.targets.record(c, result);
+        self.strategy.record(c, result);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::STANDARD_FLEET;
+    use rand::SeedableRng;
+
+    // plays 'difficulty' against seeded fleets, checking every shot it picks.
+    fn sinks_every_fleet(difficulty: Difficulty) {
+        let mut rng = StdRng::seed_from_u64(7);
+
+        for _ in 0..20 {
+            let mut board = place_fleet(&STANDARD_FLEET, 10, 10, &mut rng).unwrap();
+            let mut targets = TargetBoard::new(10, 10);
+            let mut strategy = difficulty.strategy(&STANDARD_FLEET);
+            let mut shots = 0;
+
+            while !board.is_defeated() {
+                let shot = strategy.next_shot(&targets, &mut rng).unwrap();
+                assert_eq!(targets.check_shot(shot), Ok(()), "{}", strategy.name());
+
+                let result = board.receive_shot(shot).unwrap();
+                targets.record(shot, result);
+                strategy.record(shot, result);
+
+                shots += 1;
+                assert!(shots <= 100, "{} ran out of cells", strategy.name());
+            }
+        }
+    }
+
+    #[test]
+    fn random_sinks_every_fleet() {
+        sinks_every_fleet(Difficulty::Easy);
+    }
+
+    #[test]
+    fn hunt_target_sinks_every_fleet() {
+        sinks_every_fleet(Difficulty::Medium);
+    }
+
+    #[test]
+    fn density_sinks_every_fleet() {
+        sinks_every_fleet(Difficulty::Hard);
+    }
+
+    #[test]
+    fn nothing_left_to_shoot() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut targets = TargetBoard::new(5, 5);
+        for y in 0..5 {
+            for x in 0..5 {
+                targets.record(Coord::new(x, y), ShotResult::Miss);
+            }
+        }
+
+        assert_eq!(RandomStrategy.next_shot(&targets, &mut rng), None);
+        assert_eq!(
+            HuntTargetStrategy::new(&STANDARD_FLEET).next_shot(&targets, &mut rng),
+            None
+        );
+        assert_eq!(
+            DensityStrategy::new(&STANDARD_FLEET).next_shot(&targets, &mut rng),
+            None
+        );
+    }
+
+    #[test]
+    fn placed_fleets_are_legal() {
+        let mut rng = StdRng::seed_from_u64(7);
+
+        let board = place_fleet(&STANDARD_FLEET, 10, 10, &mut rng).unwrap();
+        let placed: Vec<ShipType> = board.ships().iter().map(|ship| ship.ship_type()).collect();
+        assert_eq!(placed, STANDARD_FLEET.to_vec());
+
+        // six carriers cover more cells than a 5x5 board has.
+        let crowded = [ShipType::Carrier; 6];
+        assert!(place_fleet(&crowded, 5, 5, &mut rng).is_none());
+    }
+
+    #[test]
+    fn tracker_resolves_sunk_ships() {
+        let mut tracker = HitTracker::new(&[ShipType::Destroyer, ShipType::PatrolBoat]);
+
+        tracker.record(Coord::new(4, 4), ShotResult::Miss);
+        tracker.record(Coord::new(2, 3), ShotResult::Hit);
+        tracker.record(Coord::new(3, 3), ShotResult::Hit);
+        tracker.record(Coord::new(5, 5), ShotResult::Hit);
+        assert_eq!(tracker.unresolved.len(), 3);
+
+        tracker.record(Coord::new(1, 3), ShotResult::Sunk(ShipType::Destroyer));
+        assert_eq!(tracker.afloat, vec![ShipType::PatrolBoat]);
+        assert_eq!(tracker.unresolved, vec![Coord::new(5, 5)]);
+
+        tracker.record(Coord::new(5, 6), ShotResult::Sunk(ShipType::PatrolBoat));
+        assert!(tracker.afloat.is_empty());
+        assert!(tracker.unresolved.is_empty());
+    }
+
+    #[test]
+    fn tracker_keeps_ambiguous_hits() {
+        let mut tracker = HitTracker::new(&[ShipType::PatrolBoat]);
+
+        // a sunk patrol boat, but the hits don't line up with it.
+        tracker.record(Coord::new(0, 0), ShotResult::Hit);
+        tracker.record(Coord::new(2, 2), ShotResult::Sunk(ShipType::PatrolBoat));
+        assert_eq!(tracker.unresolved, vec![Coord::new(0, 0)]);
+    }
+
+    #[test]
+    fn hunt_target_follows_the_line() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut strategy = HuntTargetStrategy::new(&STANDARD_FLEET);
+        let mut targets = TargetBoard::new(10, 10);
+
+        for c in [Coord::new(4, 4), Coord::new(5, 4)].iter() {
+            targets.record(*c, ShotResult::Hit);
+            strategy.record(*c, ShotResult::Hit);
+        }
+
+        for _ in 0..10 {
+            let shot = strategy.next_shot(&targets, &mut rng).unwrap();
+            assert!(shot == Coord::new(3, 4) || shot == Coord::new(6, 4));
+        }
+    }
+}
@@ -0,0 +1,226 @@
+// timed effects over the boards. a state registers an animation where
+// something happened, and its 'draw' advances them all by the time the
+// last frame took, see 'clock::Clock', dropping the ones that are over.
+
+use sdl2::pixels::Color;
+use sdl2::rect::Point;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+
+use std::time::Duration;
+
+use super::engine;
+use super::geometry;
+use super::theme;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Effect {
+    // rings spreading over a missed cell.
+    Splash,
+    // a flash over a hit cell.
+    Explosion,
+    // puffs rising from a hit cell once the flash is gone.
+    Smoke,
+    // a sunk ship showing up bow first under its hits.
+    Reveal(Vec<engine::Coord>),
+}
+
+impl Effect {
+    fn length(&self) -> Duration {
+        match self {
+            Effect::Splash => Duration::from_millis(700),
+            Effect::Explosion => Duration::from_millis(400),
+            Effect::Smoke => Duration::from_millis(1500),
+            Effect::Reveal(_) => Duration::from_millis(1200),
+        }
+    }
+}
+
+struct Animation {
+    effect: Effect,
+    board: geometry::BoardGeometry,
+    cell: engine::Coord,
+    // waits this long before it starts.
+    delay: Duration,
+    elapsed: Duration,
+}
+
+impl Animation {
+    // how far along it is, from 0 to 1. 'None' while it waits.
+    fn progress(&self) -> Option<f32> {
+        let elapsed = self.elapsed.checked_sub(self.delay)?;
+
+        Some((elapsed.as_secs_f32() / self.effect.length().as_secs_f32()).min(1.0))
+    }
+
+    fn is_over(&self) -> bool {
+        self.elapsed >= self.delay + self.effect.length()
+    }
+
+    fn draw(&self, canvas: &mut Canvas<Window>, theme: &theme::Theme, progress: f32) {
+        let rect = self.board.cell_rect(self.cell);
+        let center = rect.center();
+        let radius = self.board.interval as f32 / 2.0;
+        let alpha = |from: f32| (from * (1.0 - progress)) as u8;
+
+        match &self.effect {
+            Effect::Splash => {
+                // the second ring trails the first.
+                for lag in [0.0, 0.3].iter() {
+                    let p = progress - lag;
+                    if p > 0.0 {
+                        canvas.set_draw_color(Color::RGBA(200, 230, 255, alpha(255.0)));
+                        draw_circle(canvas, center, (radius * p * 1.2) as i32);
+                    }
+                }
+            }
+
+            Effect::Explosion => {
+                // grows quickly, then burns down.
+                let size = (progress * std::f32::consts::PI).sin();
+                canvas.set_draw_color(Color::RGBA(255, 140, 0, alpha(255.0)));
+                fill_circle(canvas, center, (radius * size) as i32);
+                canvas.set_draw_color(Color::RGBA(255, 240, 120, alpha(255.0)));
+                fill_circle(canvas, center, (radius * size / 2.0) as i32);
+            }
+
+            Effect::Smoke => {
+                canvas.set_draw_color(Color::RGBA(120, 120, 120, alpha(160.0)));
+                for (i, drift) in [-0.3f32, 0.0, 0.3].iter().enumerate() {
+                    let rise = radius * 2.0 * progress * (1.0 + i as f32 / 4.0);
+                    fill_circle(
+                        canvas,
+                        center.offset((radius * drift) as i32, -rise as i32),
+                        (radius / 3.0 * (1.0 + progress)) as i32,
+                    );
+                }
+            }
+
+            Effect::Reveal(cells) => {
+                // the whole ship is out halfway through, the hits stay on top.
+                let shown = ((progress * 2.0).min(1.0) * cells.len() as f32).ceil() as usize;
+                theme.draw_ship(canvas, &self.board, &cells[..shown]);
+                theme.draw_markers(canvas, &self.board, theme::Sprite::Hit, cells);
+
+                canvas.set_draw_color(Color::RGBA(255, 255, 255, alpha(255.0)));
+                canvas
+                    .draw_rects(&self.board.cell_rects(&cells[..shown]))
+                    .unwrap();
+            }
+        }
+    }
+}
+
+pub struct Animations {
+    running: Vec<Animation>,
+}
+
+impl Animations {
+    pub fn new() -> Animations {
+        Animations {
+            running: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, effect: Effect, board: geometry::BoardGeometry, cell: engine::Coord) {
+        self.add_after(Duration::from_secs(0), effect, board, cell);
+    }
+
+    pub fn add_after(
+        &mut self,
+        delay: Duration,
+        effect: Effect,
+        board: geometry::BoardGeometry,
+        cell: engine::Coord,
+    ) {
+        self.running.push(Animation {
+            effect: effect,
+            board: board,
+            cell: cell,
+            delay: delay,
+            elapsed: Duration::from_secs(0),
+        });
+    }
+
+    // what a shot at 'cell' answered with 'result' looks like. 'ship' is
+    // where a sunk ship lay, if that is known.
+    pub fn shot(
+        &mut self,
+        board: geometry::BoardGeometry,
+        cell: engine::Coord,
+        result: engine::ShotResult,
+        ship: Option<Vec<engine::Coord>>,
+    ) {
+        if !result.is_hit() {
+            self.add(Effect::Splash, board, cell);
+            return;
+        }
+
+        self.add(Effect::Explosion, board, cell);
+        self.add_after(Effect::Explosion.length(), Effect::Smoke, board, cell);
+
+        if let Some(mut ship) = ship {
+            ship.sort_by_key(|c| (c.x, c.y));
+            self.add(Effect::Reveal(ship), board, cell);
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        !self.running.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.running.clear();
+    }
+
+    // advances every animation by 'dt', then draws the ones that started.
+    pub fn draw(&mut self, canvas: &mut Canvas<Window>, theme: &theme::Theme, dt: Duration) {
+        for animation in self.running.iter_mut() {
+            animation.elapsed += dt;
+        }
+        self.running.retain(|animation| !animation.is_over());
+
+        // ship reveals go under the flashes and the smoke.
+        self.running
+            .sort_by_key(|animation| match animation.effect {
+                Effect::Reveal(_) => 0,
+                _ => 1,
+            });
+
+        for animation in self.running.iter() {
+            if let Some(progress) = animation.progress() {
+                animation.draw(canvas, theme, progress);
+            }
+        }
+    }
+}
+
+// goes from 0 up to 1 and back down every 'period', for things that pulse.
+pub fn pulse(time: Duration, period: Duration) -> f32 {
+    let phase = time.as_secs_f32() / period.as_secs_f32();
+
+    0.5 - 0.5 * (phase * 2.0 * std::f32::consts::PI).cos()
+}
+
+// circles in the canvas' draw color, one line or point per row.
+fn fill_circle(canvas: &mut Canvas<Window>, center: Point, radius: i32) {
+    for dy in -radius..=radius {
+        let dx = ((radius * radius - dy * dy) as f32).sqrt() as i32;
+        canvas
+            .draw_line(center.offset(-dx, dy), center.offset(dx, dy))
+            .unwrap();
+    }
+}
+
+fn draw_circle(canvas: &mut Canvas<Window>, center: Point, radius: i32) {
+    let mut points: Vec<Point> = Vec::new();
+    for dy in -radius..=radius {
+        let dx = ((radius * radius - dy * dy) as f32).sqrt() as i32;
+        points.push(center.offset(-dx, dy));
+        points.push(center.offset(dx, dy));
+        // the sides, where the rows alone would leave gaps.
+        points.push(center.offset(dy, -dx));
+        points.push(center.offset(dy, dx));
+    }
+    canvas.draw_points(&points[..]).unwrap();
+}
@@ -0,0 +1,206 @@
+// sound effects and background music through sdl2's mixer. the sounds are
+// made up from notes when the game starts, so no sound files ship with it.
+// without an audio device, e.g. on a headless box, every call does nothing.
+
+use sdl2::mixer::Channel;
+use sdl2::mixer::Chunk;
+use sdl2::AudioSubsystem;
+
+use super::config;
+use super::prefs;
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Sound {
+    Place,
+    Fire,
+    Hit,
+    Miss,
+    Sunk,
+    Victory,
+    Defeat,
+}
+
+const SOUNDS: [Sound; 7] = [
+    Sound::Place,
+    Sound::Fire,
+    Sound::Hit,
+    Sound::Miss,
+    Sound::Sunk,
+    Sound::Victory,
+    Sound::Defeat,
+];
+
+// effects may overlap on these, the music has one of its own.
+const CHANNELS: i32 = 8;
+const MUSIC_CHANNEL: Channel = Channel(0);
+// the music plays this many times quieter than the effects.
+const MUSIC_DAMPING: i32 = 3;
+// of the largest sample, so notes don't clip when they overlap.
+const AMPLITUDE: f32 = 0.25;
+
+// (frequency in hz, length in ms), a zero frequency rests.
+type Note = (u32, u32);
+
+fn notes(sound: Sound) -> &'static [Note] {
+    match sound {
+        Sound::Place => &[(440, 40), (660, 60)],
+        Sound::Fire => &[(880, 30), (660, 30), (440, 40)],
+        Sound::Hit => &[(220, 80), (110, 160)],
+        Sound::Miss => &[(330, 60), (0, 20), (262, 90)],
+        Sound::Sunk => &[(196, 100), (147, 100), (98, 300)],
+        Sound::Victory => &[(523, 120), (659, 120), (784, 120), (1047, 360)],
+        Sound::Defeat => &[(392, 160), (330, 160), (262, 160), (196, 480)],
+    }
+}
+
+// a slow a minor walk, looped.
+const MUSIC: &[Note] = &[
+    (110, 400),
+    (165, 400),
+    (220, 400),
+    (165, 400),
+    (98, 400),
+    (147, 400),
+    (196, 400),
+    (147, 400),
+    (87, 400),
+    (131, 400),
+    (175, 400),
+    (131, 400),
+    (82, 400),
+    (123, 400),
+    (165, 400),
+    (123, 400),
+];
+
+pub struct Audio {
+    // 'None' when there is no device to play on.
+    device: Option<Device>,
+}
+
+struct Device {
+    // closing the subsystem would stop the mixer.
+    _subsystem: AudioSubsystem,
+    effects: Vec<(Sound, Chunk)>,
+    music: Chunk,
+}
+
+impl Audio {
+    pub fn new(sdl_context: &sdl2::Sdl, prefs: &prefs::Prefs) -> Audio {
+        let audio = match Audio::open(sdl_context) {
+            Ok(device) => Audio {
+                device: Some(device),
+            },
+            Err(err) => {
+                println!("<Audio> playing without sound: {}", err);
+                Audio { device: None }
+            }
+        };
+
+        audio.apply(prefs);
+        audio
+    }
+
+    fn open(sdl_context: &sdl2::Sdl) -> Result<Device, String> {
+        let subsystem = sdl_context.audio()?;
+
+        sdl2::mixer::open_audio(
+            sdl2::mixer::DEFAULT_FREQUENCY,
+            sdl2::mixer::DEFAULT_FORMAT,
+            sdl2::mixer::DEFAULT_CHANNELS,
+            1024,
+        )?;
+
+        // the device may have given us something else than we asked for.
+        let (frequency, format, channels) = sdl2::mixer::query_spec()?;
+        if format != sdl2::mixer::AUDIO_S16SYS {
+            return Err(format!("unsupported sample format {:#x}", format));
+        }
+
+        sdl2::mixer::allocate_channels(CHANNELS);
+        sdl2::mixer::reserve_channels(1);
+
+        let load = |notes: &[Note]| Chunk::from_raw_buffer(synthesize(notes, frequency, channels));
+
+        let mut effects: Vec<(Sound, Chunk)> = Vec::with_capacity(SOUNDS.len());
+        for sound in SOUNDS.iter() {
+            effects.push((*sound, load(notes(*sound))?));
+        }
+
+        Ok(Device {
+            _subsystem: subsystem,
+            effects: effects,
+            music: load(MUSIC)?,
+        })
+    }
+
+    // volume, mute and music as 'prefs' has them.
+    pub fn apply(&self, prefs: &prefs::Prefs) {
+        let device = match self.device.as_ref() {
+            Some(device) => device,
+            None => return,
+        };
+
+        let volume = if prefs.is_muted {
+            0
+        } else {
+            prefs.volume * sdl2::mixer::MAX_VOLUME / config::MAX_VOLUME
+        };
+        Channel::all().set_volume(volume);
+        MUSIC_CHANNEL.set_volume(volume / MUSIC_DAMPING);
+
+        if !prefs.has_music {
+            MUSIC_CHANNEL.halt();
+        } else if !MUSIC_CHANNEL.is_playing() {
+            if let Err(err) = MUSIC_CHANNEL.play(&device.music, -1) {
+                println!("<Audio> couldn't play the music: {}", err);
+            }
+        }
+    }
+
+    pub fn play(&self, sound: Sound) {
+        let device = match self.device.as_ref() {
+            Some(device) => device,
+            None => return,
+        };
+
+        if let Some((_, chunk)) = device.effects.iter().find(|(s, _)| *s == sound) {
+            // all channels busy, the sound is skipped.
+            if let Err(err) = Channel::all().play(chunk, 0) {
+                println!("<Audio> couldn't play {:?}: {}", sound, err);
+            }
+        }
+    }
+}
+
+// square waves fading out over each note, as the device's native 16 bit
+// samples with every channel the same.
+fn synthesize(notes: &[Note], frequency: i32, channels: i32) -> Box<[u8]> {
+    let mut bytes: Vec<u8> = Vec::new();
+
+    for (pitch, length) in notes.iter() {
+        let samples = (frequency as u32 * length / 1000) as usize;
+
+        for i in 0..samples {
+            let value = if *pitch == 0 {
+                0.0
+            } else {
+                let period = frequency as f32 / *pitch as f32;
+                let fade = 1.0 - i as f32 / samples as f32;
+                let sign = if (i as f32 % period) < period / 2.0 {
+                    1.0
+                } else {
+                    -1.0
+                };
+
+                sign * fade * AMPLITUDE * i16::MAX as f32
+            };
+
+            for _ in 0..channels {
+                bytes.extend_from_slice(&(value as i16).to_ne_bytes());
+            }
+        }
+    }
+
+    bytes.into_boxed_slice()
+}
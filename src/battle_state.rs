@@ -9,89 +9,192 @@ use sdl2::EventPump;
 use std::vec::Vec;
 
 use async_trait::async_trait;
-use mini_redis::Result;
 use std::sync::Arc;
-use tokio::sync::Mutex;
-use tokio::task::JoinHandle;
+use std::time::Duration;
+use tokio::sync::{Mutex, Notify};
 
-use super::chat;
+use super::animation;
+use super::audio;
+use super::choose_state;
+use super::commitment;
 use super::config;
-use super::ship;
+use super::engine;
+use super::error_state;
+use super::fleet_panel;
+use super::geometry;
+use super::layout;
+use super::peer;
+use super::protocol;
+use super::replay;
+use super::session;
 use super::state;
 use super::stats_state;
+use super::text;
+use super::theme;
 
-type ServerHandle = JoinHandle<mini_redis::Result<()>>;
+// one beat of the crosshair while it's our turn.
+const PULSE_PERIOD: Duration = Duration::from_millis(1000);
 
 pub struct BattleState {
-    board_lines: Vec<(Point, Point)>,
-    my_ship_points: Arc<Mutex<Vec<Point>>>,
+    layout: layout::Layout,
+    // my fleet on the left, the targeting grid on the right.
+    boards: (geometry::BoardGeometry, geometry::BoardGeometry),
     my_shot: Point,
 
-    opponent_hit_shots: Arc<Mutex<Vec<Point>>>,
-    opponent_miss_shots: Arc<Mutex<Vec<Point>>>,
+    // my fleet and the opponent's shots at it.
+    my_board: Arc<Mutex<engine::Board>>,
+    // my shots at the opponent's fleet.
+    my_targets: Arc<Mutex<engine::TargetBoard>>,
+    // both sides sail the same fleet.
+    fleet: Vec<engine::ShipType>,
 
-    my_hit_shots: Arc<Mutex<Vec<Point>>>,
-    my_miss_shots: Arc<Mutex<Vec<Point>>>,
-
-    chat: Arc<Mutex<chat::Chat>>,
-
-    server_handle: Option<ServerHandle>,
+    session: session::Session,
+    replay: Arc<Mutex<replay::Replay>>,
 
     is_quit: Arc<Mutex<bool>>,
-    is_init: bool,
+    // set by a turn task that lost the opponent.
+    error: Arc<Mutex<Option<String>>>,
+    // the opponent went quiet, we keep waiting for them to resume.
+    is_disconnected: Arc<Mutex<bool>>,
+    // what their revealed fleet says about their answers.
+    verdict: Arc<Mutex<commitment::Verdict>>,
     is_send_shot: Arc<Mutex<bool>>,
     is_recieve_shot: Arc<Mutex<bool>>,
+    // replay events we already played a sound and an animation for.
+    heard: usize,
+    animations: animation::Animations,
+    // how long it has been our turn, the crosshair pulses along.
+    turn_time: Duration,
 }
 
 impl BattleState {
     pub async fn new(
-        board_lines: Vec<(Point, Point)>,
-        my_ships: Vec<ship::Ship>,
-        addr: String,
-        server_handle: Option<ServerHandle>,
-        receive_channel_key: String,
-        send_channel_key: String,
-    ) -> Result<BattleState> {
-        let mut my_ship_points: Vec<Point> = Vec::new();
-        for ship in my_ships.iter() {
-            for ship_point in ship.body.iter() {
-                my_ship_points.push(*ship_point);
-            }
+        my_board: engine::Board,
+        session: session::Session,
+        mut replay: replay::Replay,
+    ) -> BattleState {
+        for ship in my_board.ships().iter() {
+            replay.record(replay::Event::Place(
+                ship.ship_type(),
+                ship.cells().to_vec(),
+            ));
         }
 
-        Ok(BattleState {
-            board_lines: board_lines,
-            my_ship_points: Arc::new(Mutex::new(my_ship_points)),
-            my_shot: Point::new(
-                (config::BOARD_LENGTH / 2) as i32,
-                (config::BOARD_LENGTH / 2) as i32,
-            ),
+        let commitment =
+            commitment::Commitment::new(&commitment::fleet_of(&my_board), &mut rand::thread_rng());
+        let digest = commitment.digest.clone();
+        replay.commitment = Some(commitment);
+
+        let mut locked_peer = session.peer.lock().await;
+
+        // our fleet and salt are all we need to pick the match up again after
+        // dropping out, the opponent tells us the shots. 'save_match' keeps
+        // them on this machine, the salt must not leave it before the reveal.
+        if let Err(err) = locked_peer.save_match(replay.encode().into()).await {
+            println!("<BattleState> couldn't save the match: {}", err);
+        }
 
-            opponent_hit_shots: Arc::new(Mutex::new(Vec::new())),
-            opponent_miss_shots: Arc::new(Mutex::new(Vec::new())),
+        // before our first shot, so our answers can be checked at the end.
+        // if this fails so will the first turn, which says why.
+        if let Err(err) = locked_peer
+            .send_message(&protocol::Message::Commit(digest))
+            .await
+        {
+            println!("<BattleState> couldn't commit to our fleet: {}", err);
+        }
 
-            my_hit_shots: Arc::new(Mutex::new(Vec::new())),
-            my_miss_shots: Arc::new(Mutex::new(Vec::new())),
+        drop(locked_peer);
 
-            chat: Arc::new(Mutex::new(
-                chat::Chat::new(addr, receive_channel_key, send_channel_key).await?,
-            )),
+        BattleState::resume(replay, session)
+    }
 
-            server_handle: server_handle,
+    // continues the match 'replay' recorded so far.
+    pub fn resume(replay: replay::Replay, session: session::Session) -> BattleState {
+        let (my_board, my_targets) = replay.position(replay.events.len());
+        let fleet: Vec<engine::ShipType> = my_board
+            .ships()
+            .iter()
+            .map(|ship| ship.ship_type())
+            .collect();
+
+        // the first player shoots whenever both sides shot as often.
+        let shots = replay
+            .events
+            .iter()
+            .filter(|event| matches!(event, replay::Event::Shot(..)))
+            .count();
+        let incoming = replay
+            .events
+            .iter()
+            .filter(|event| matches!(event, replay::Event::Incoming(..)))
+            .count();
+        let is_my_turn = if session.is_first {
+            shots == incoming
+        } else {
+            shots < incoming
+        };
+
+        // a resumed match doesn't sound or show its old shots again.
+        let heard = replay.events.len();
+
+        let layout = layout::Layout::default();
+
+        BattleState {
+            layout: layout,
+            boards: geometry::BoardGeometry::side_by_side(
+                &layout,
+                my_board.width(),
+                my_board.height(),
+            ),
+            my_shot: Point::new(my_board.width() / 2, my_board.height() / 2),
+
+            my_board: Arc::new(Mutex::new(my_board)),
+            my_targets: Arc::new(Mutex::new(my_targets)),
+            fleet: fleet,
+
+            session: session,
+            replay: Arc::new(Mutex::new(replay)),
 
             is_quit: Arc::new(Mutex::new(false)),
-            // this is replaced on first iteration.
-            is_init: false,
-            is_send_shot: Arc::new(Mutex::new(false)),
-            is_recieve_shot: Arc::new(Mutex::new(false)),
-        })
+            error: Arc::new(Mutex::new(None)),
+            is_disconnected: Arc::new(Mutex::new(false)),
+            verdict: Arc::new(Mutex::new(commitment::Verdict::Unverified)),
+            // the host, or the player facing the computer, shoots first.
+            is_send_shot: Arc::new(Mutex::new(is_my_turn)),
+            is_recieve_shot: Arc::new(Mutex::new(!is_my_turn)),
+            heard: heard,
+            animations: animation::Animations::new(),
+            turn_time: Duration::from_secs(0),
+        }
+    }
+
+    // shoots at the crosshair, if it's our turn and we didn't shoot there yet.
+    async fn fire(&mut self, audio: &audio::Audio) {
+        let is_send_shot = self.is_send_shot.clone();
+        let mut is_send_shot = is_send_shot.lock().await;
+
+        let is_new_shot = self
+            .my_targets
+            .lock()
+            .await
+            .check_shot(engine::Coord::new(self.my_shot.x, self.my_shot.y))
+            .is_ok();
+
+        if *is_send_shot && is_new_shot {
+            *is_send_shot = false;
+            audio.play(audio::Sound::Fire);
+            self.snd_rcv(engine::Coord::new(self.my_shot.x, self.my_shot.y))
+                .await;
+        }
+
+        drop(is_send_shot);
     }
 
     fn is_valid_shot_move(&self, dxy: &Point) -> bool {
         if self.my_shot.x + dxy.x < 0
-            || self.my_shot.x + dxy.x >= config::BOARD_LENGTH as i32
+            || self.my_shot.x + dxy.x >= self.boards.1.width
             || self.my_shot.y + dxy.y < 0
-            || self.my_shot.y + dxy.y >= config::BOARD_LENGTH as i32
+            || self.my_shot.y + dxy.y >= self.boards.1.height
         {
             return false;
         }
@@ -107,192 +210,263 @@ impl BattleState {
     }
 
     async fn rcv_snd(&mut self) {
-        let chat = self.chat.clone();
+        let peer = self.session.peer.clone();
         let is_quit = self.is_quit.clone();
         let is_send_shot = self.is_send_shot.clone();
-        let opponent_miss_shots = self.opponent_miss_shots.clone();
-        let opponent_hit_shots = self.opponent_hit_shots.clone();
-        let my_ship_points = self.my_ship_points.clone();
+        let my_board = self.my_board.clone();
+        let replay = self.replay.clone();
+        let error = self.error.clone();
+        let is_disconnected = self.is_disconnected.clone();
+        let resigned = self.session.resigned.clone();
+        let verdict = self.verdict.clone();
 
         tokio::spawn(async move {
-            let mut chat = chat.lock().await;
-            if let Ok(Some(msg)) = chat.receive().await {
-                // parse the recieved shot.
-                let msg_str = format!("{:?}", msg.content)
-                    .strip_prefix("b\"")
-                    .unwrap()
-                    .to_string()
-                    .strip_suffix("\"")
-                    .unwrap()
-                    .to_string();
-
-                let words: Vec<&str> = msg_str.split(" ").collect();
-                let x: i32 = words[0].parse().unwrap();
-                let y: i32 = words[1].parse().unwrap();
-
-                let shot = Point::new(x, y);
-
-                // verify hit.
-                let my_ship_points = my_ship_points.lock().await;
-
-                let mut is_hit = false;
-                for ship_point in my_ship_points.iter() {
-                    if shot == *ship_point {
-                        is_hit = true;
-                        break;
-                    }
-                }
+            let mut peer = peer.lock().await;
 
-                // Cache value and send STAT.
-                if is_hit {
-                    let mut opponent_hit_shots = opponent_hit_shots.lock().await;
+            let shot = match next_message(&mut **peer, &replay, None, &is_disconnected, &resigned)
+                .await
+            {
+                Ok(protocol::Message::Shot(shot)) => shot,
 
-                    // make sure is not repeated.
-                    if !opponent_hit_shots.contains(&shot) {
-                        opponent_hit_shots.push(shot);
-                    }
+                // the opponent sank our last ship.
+                Ok(protocol::Message::GameOver) => {
+                    *verdict.lock().await =
+                        exchange_reveals(&mut **peer, &replay, &is_disconnected, &resigned, false)
+                            .await;
 
-                    // make sure is not game over.
-                    if my_ship_points.len() == opponent_hit_shots.len() {
-                        let mut is_quit = is_quit.lock().await;
-                        *is_quit = true;
+                    let mut is_quit = is_quit.lock().await;
+                    *is_quit = true;
+                    return;
+                }
 
-                        let _ = chat.send("GAMEOVER".into()).await;
-                    } else {
-                        let _ = chat.send("HIT".into()).await;
-                    }
-                } else {
-                    let mut opponent_miss_shots = opponent_miss_shots.lock().await;
+                Ok(protocol::Message::Resign) => {
+                    *verdict.lock().await = commitment::Verdict::Resigned;
 
-                    // make sure is not repeated.
-                    if !opponent_miss_shots.contains(&shot) {
-                        opponent_miss_shots.push(shot);
-                    }
+                    let mut is_quit = is_quit.lock().await;
+                    *is_quit = true;
+                    return;
+                }
 
-                    let _ = chat.send("MISS".into()).await;
+                // the match server ended the match, we broke one of its rules.
+                Ok(protocol::Message::Foul(reason)) => {
+                    *error.lock().await = Some(format!("you forfeit the match: {}", reason));
+                    return;
                 }
 
-                drop(my_ship_points);
+                // out of turn, the two sides no longer agree on the match.
+                Ok(other) => {
+                    *error.lock().await = Some(format!("protocol error: unexpected {:?}", other));
+                    return;
+                }
+
+                Err(err) => {
+                    *error.lock().await = Some(format!("lost the opponent: {}", err));
+                    return;
+                }
+            };
+
+            // resolve the shot against my fleet.
+            let mut my_board = my_board.lock().await;
+
+            let result = match my_board.receive_shot(shot) {
+                Ok(result) => result,
+                // a repeated shot gets a plain answer, it can't sink anything twice.
+                Err(_) => match my_board.ship_at(shot) {
+                    Some(_) => engine::ShotResult::Hit,
+                    None => engine::ShotResult::Miss,
+                },
+            };
+
+            replay
+                .lock()
+                .await
+                .record(replay::Event::Incoming(shot, result));
+
+            if let Err(err) = peer
+                .send_message(&protocol::Message::ShotResult(result))
+                .await
+            {
+                *error.lock().await = Some(format!("lost the opponent: {}", err));
+                return;
+            }
+
+            // make sure is not game over.
+            if my_board.is_defeated() {
+                drop(my_board);
+
+                // we lost either way, nothing to do if they don't hear it.
+                let _ = peer.send_message(&protocol::Message::GameOver).await;
 
-                // we can send a shot now.
-                let mut is_send_shot = is_send_shot.lock().await;
-                *is_send_shot = true;
+                *verdict.lock().await =
+                    exchange_reveals(&mut **peer, &replay, &is_disconnected, &resigned, true).await;
+
+                let mut is_quit = is_quit.lock().await;
+                *is_quit = true;
+                return;
             }
+
+            drop(my_board);
+
+            // we can send a shot now.
+            let mut is_send_shot = is_send_shot.lock().await;
+            *is_send_shot = true;
         });
     }
 
-    async fn snd_rcv(&mut self, msg: String) {
-        let chat = self.chat.clone();
+    async fn snd_rcv(&mut self, shot: engine::Coord) {
+        let peer = self.session.peer.clone();
         let is_quit = self.is_quit.clone();
         let is_recieve_shot = self.is_recieve_shot.clone();
-        let my_miss_shots = self.my_miss_shots.clone();
-        let my_hit_shots = self.my_hit_shots.clone();
+        let my_targets = self.my_targets.clone();
+        let replay = self.replay.clone();
+        let error = self.error.clone();
+        let is_disconnected = self.is_disconnected.clone();
+        let verdict = self.verdict.clone();
+        let resigned = self.session.resigned.clone();
 
         tokio::spawn(async move {
-            let msg2 = msg.clone();
-
-            let mut chat = chat.lock().await;
-            let _ = chat.send(msg.into()).await;
-
-            let words: Vec<&str> = msg2.split(" ").collect();
-            let x: i32 = words[0].parse().unwrap();
-            let y: i32 = words[1].parse().unwrap();
-            let shot = Point::new(x, y);
+            let mut peer = peer.lock().await;
 
             // recieve stat.
-            if let Ok(Some(msg)) = chat.receive().await {
-                // no neeed to strip because we're using 'contains'
-                let msg_str = format!("{:?}", msg.content);
-
-                if msg_str.contains("HIT") {
-                    let mut my_hit_shots = my_hit_shots.lock().await;
-                    if !my_hit_shots.contains(&shot) {
-                        my_hit_shots.push(shot);
-                    }
-                } else if msg_str.contains("MISS") {
-                    let mut my_miss_shots = my_miss_shots.lock().await;
-                    if !my_miss_shots.contains(&shot) {
-                        my_miss_shots.push(shot);
+            let answer = match peer.send_message(&protocol::Message::Shot(shot)).await {
+                Ok(()) => {
+                    next_message(
+                        &mut **peer,
+                        &replay,
+                        Some(shot),
+                        &is_disconnected,
+                        &resigned,
+                    )
+                    .await
+                }
+                Err(err) => Err(err),
+            };
+
+            match answer {
+                Ok(protocol::Message::ShotResult(result)) => {
+                    let mut my_targets = my_targets.lock().await;
+                    my_targets.record(shot, result);
+
+                    let mut locked_replay = replay.lock().await;
+                    locked_replay.record(replay::Event::Shot(shot, result));
+
+                    // their answers hit every cell of the fleet they committed
+                    // to, or leave it nowhere to be. the match is over whether
+                    // or not they say so, and they owe us their fleet.
+                    let settings = &locked_replay.settings;
+                    let fleet_cells = settings.fleet_cells();
+                    let water_cells = (settings.width * settings.height) as usize - fleet_cells;
+                    if my_targets.hits().len() >= fleet_cells
+                        || my_targets.misses().len() > water_cells
+                    {
+                        drop(locked_replay);
+                        drop(my_targets);
+
+                        *verdict.lock().await = exchange_reveals(
+                            &mut **peer,
+                            &replay,
+                            &is_disconnected,
+                            &resigned,
+                            true,
+                        )
+                        .await;
+
+                        let mut is_quit = is_quit.lock().await;
+                        *is_quit = true;
+                        return;
                     }
-                } else if msg_str.contains("GAMEOVER") {
+                }
+
+                Ok(protocol::Message::Resign) => {
+                    *verdict.lock().await = commitment::Verdict::Resigned;
+
                     let mut is_quit = is_quit.lock().await;
                     *is_quit = true;
+                    return;
+                }
+
+                // the match server ended the match, we broke one of its rules.
+                Ok(protocol::Message::Foul(reason)) => {
+                    *error.lock().await = Some(format!("you forfeit the match: {}", reason));
+                    return;
+                }
+
+                // out of turn, the two sides no longer agree on the match.
+                Ok(other) => {
+                    *error.lock().await = Some(format!("protocol error: unexpected {:?}", other));
+                    return;
                 }
 
-                // we can recieve a shot now.
-                let mut is_recieve_shot = is_recieve_shot.lock().await;
-                *is_recieve_shot = true;
+                Err(err) => {
+                    *error.lock().await = Some(format!("lost the opponent: {}", err));
+                    return;
+                }
             }
+
+            // we can recieve a shot now.
+            let mut is_recieve_shot = is_recieve_shot.lock().await;
+            *is_recieve_shot = true;
         });
     }
 
+    // a sound and an animation for every shot answered since the last call,
+    // ours or theirs.
+    async fn play_results(&mut self, audio: &audio::Audio) {
+        // the boards before the replay, as the turn tasks lock them.
+        let my_board = self.my_board.lock().await;
+        let my_targets = self.my_targets.lock().await;
+        let replay = self.replay.lock().await;
+
+        for event in replay.events[self.heard..].iter() {
+            let (board, c, result, ship) = match event {
+                replay::Event::Shot(c, result) => (
+                    self.boards.1,
+                    *c,
+                    *result,
+                    match result {
+                        engine::ShotResult::Sunk(ship_type) => {
+                            my_targets.sunk_cells(*c, *ship_type)
+                        }
+                        _ => None,
+                    },
+                ),
+                replay::Event::Incoming(c, result) => (
+                    self.boards.0,
+                    *c,
+                    *result,
+                    match result {
+                        engine::ShotResult::Sunk(_) => {
+                            my_board.ship_at(*c).map(|ship| ship.cells().to_vec())
+                        }
+                        _ => None,
+                    },
+                ),
+                replay::Event::Place(..) => continue,
+            };
+
+            audio.play(match result {
+                engine::ShotResult::Miss => audio::Sound::Miss,
+                engine::ShotResult::Hit => audio::Sound::Hit,
+                engine::ShotResult::Sunk(_) => audio::Sound::Sunk,
+            });
+            self.animations.shot(board, c, result, ship);
+        }
+
+        self.heard = replay.events.len();
+    }
+
     async fn update(&mut self) {
-        let is_send_shot = self.is_send_shot.clone();
         let is_recieve_shot = self.is_recieve_shot.clone();
-
-        let mut is_send_shot = is_send_shot.lock().await;
         let mut is_recieve_shot = is_recieve_shot.lock().await;
 
-        // make sure we're ready.
-        if !self.is_init {
-            self.is_init = true;
-            match self.server_handle {
-                Some(_) => {
-                    *is_send_shot = true;
-                    *is_recieve_shot = false;
-                }
-
-                None => {
-                    *is_send_shot = false;
-                    *is_recieve_shot = true;
-                }
-            }
-        }
-
         if *is_recieve_shot {
             *is_recieve_shot = false;
 
-            drop(is_send_shot);
             drop(is_recieve_shot);
 
             self.rcv_snd().await;
         }
     }
-
-    async fn draw_shots(
-        &self,
-        canvas: &mut Canvas<Window>,
-        color: Color,
-        shots: &Arc<Mutex<Vec<Point>>>,
-    ) {
-        let x_offset: i32 = (config::WINDOW_WIDTH as i32 - config::WINDOW_HEIGHT as i32) / 2;
-        let min_wh: i32 = std::cmp::min(config::WINDOW_WIDTH as i32, config::WINDOW_HEIGHT as i32);
-
-        let x_interval: i32 = min_wh / 10;
-        let y_interval: i32 = min_wh / 10;
-
-        canvas.set_draw_color(color);
-
-        let mut cache: Vec<Rect> = Vec::new();
-
-        let shots = shots.clone();
-        let shots = shots.lock().await;
-
-        for point in shots.iter() {
-            let rect = Rect::new(
-                point.x * x_interval + x_offset,
-                point.y * y_interval,
-                x_interval as u32,
-                y_interval as u32,
-            );
-            cache.push(rect);
-        }
-
-        drop(shots);
-
-        canvas.fill_rects(&cache[..]).unwrap();
-        canvas.draw_rects(&cache[..]).unwrap();
-    }
 }
 
 #[async_trait(?Send)]
@@ -301,30 +475,53 @@ impl state::State for BattleState {
         &mut self,
         event_pump: &mut EventPump,
         next_state: &mut Option<state::NextState>,
+        audio: &audio::Audio,
     ) {
         self.update().await;
+        self.play_results(audio).await;
 
         let is_quit = self.is_quit.clone();
         let is_quit = is_quit.lock().await;
         if *is_quit {
-            let opponent_hit_shots = self.opponent_hit_shots.clone();
-            let opponent_hit_shots = opponent_hit_shots.lock().await;
+            let my_board = self.my_board.lock().await;
+            let my_targets = self.my_targets.lock().await;
+            let replay = self.replay.lock().await;
 
-            let opponent_miss_shots = self.opponent_miss_shots.clone();
-            let opponent_miss_shots = opponent_miss_shots.lock().await;
+            match replay.save(std::path::Path::new(config::REPLAY_DIR)) {
+                Ok(path) => println!("<BattleState> replay saved to {}", path.display()),
+                Err(err) => println!("<BattleState> couldn't save replay: {}", err),
+            }
+
+            // the match is over, there is nothing to resume anymore.
+            if let Err(err) = self
+                .session
+                .peer
+                .lock()
+                .await
+                .save_match(bytes::Bytes::new())
+                .await
+            {
+                println!("<BattleState> couldn't clear the match: {}", err);
+            }
 
-            let my_hit_shots = self.my_hit_shots.clone();
-            let my_hit_shots = my_hit_shots.lock().await;
+            let verdict = self.verdict.lock().await.clone();
 
-            let my_miss_shots = self.my_miss_shots.clone();
-            let my_miss_shots = my_miss_shots.lock().await;
+            // a resigned opponent leaves our fleet afloat too.
+            audio.play(match &verdict {
+                commitment::Verdict::Forfeit(_) | commitment::Verdict::Resigned => {
+                    audio::Sound::Victory
+                }
+                _ if my_board.is_defeated() => audio::Sound::Defeat,
+                _ => audio::Sound::Victory,
+            });
 
             next_state.replace(state::NextState::Update(Box::new(
                 stats_state::StatsState::new(
-                    opponent_hit_shots.clone(),
-                    opponent_miss_shots.clone(),
-                    my_hit_shots.clone(),
-                    my_miss_shots.clone(),
+                    my_board.clone(),
+                    my_targets.clone(),
+                    replay.clone(),
+                    verdict,
+                    self.session.hand_over(),
                 ),
             )));
             return;
@@ -332,30 +529,46 @@ impl state::State for BattleState {
 
         drop(is_quit);
 
+        if let Some(err) = self.error.lock().await.take() {
+            next_state.replace(state::NextState::Update(Box::new(
+                error_state::ErrorState::new(err, None),
+            )));
+            return;
+        }
+
         for event in event_pump.poll_iter() {
+            // an open chat gets the keys before the crosshair does.
+            if let Some(chat_panel) = self.session.chat_panel.as_mut() {
+                if chat_panel.handle_event(&event).await {
+                    continue;
+                }
+            }
+
             match event {
                 Event::Quit { .. } => {
+                    self.session.resign().await;
                     next_state.replace(state::NextState::Quit);
                     return;
                 }
 
                 Event::KeyDown { keycode, .. } => match keycode {
                     Some(Keycode::Escape) => {
+                        self.session.resign().await;
                         next_state.replace(state::NextState::Quit);
                         return;
                     }
 
-                    Some(Keycode::Return) => {
-                        let is_send_shot = self.is_send_shot.clone();
-                        let mut is_send_shot = is_send_shot.lock().await;
-
-                        if *is_send_shot {
-                            *is_send_shot = false;
-                            self.snd_rcv(format!("{} {}", self.my_shot.x, self.my_shot.y))
-                                .await;
-                        }
+                    // giving up on an opponent who may still come back.
+                    Some(Keycode::Backspace) if *self.is_disconnected.lock().await => {
+                        self.session.resign().await;
+                        next_state.replace(state::NextState::Update(Box::new(
+                            choose_state::ChooseState::new(),
+                        )));
+                        return;
+                    }
 
-                        drop(is_send_shot);
+                    Some(Keycode::Return) => {
+                        self.fire(audio).await;
                     }
 
                     Some(Keycode::W) | Some(Keycode::Up) => {
@@ -379,6 +592,25 @@ impl state::State for BattleState {
                     }
                 },
 
+                // the crosshair follows the mouse, a click fires at it.
+                Event::MouseMotion { x, y, .. } => {
+                    if let Some(c) = self.boards.1.cell_at(x, y) {
+                        self.my_shot = Point::new(c.x, c.y);
+                    }
+                }
+
+                Event::MouseButtonDown {
+                    mouse_btn: sdl2::mouse::MouseButton::Left,
+                    x,
+                    y,
+                    ..
+                } => {
+                    if let Some(c) = self.boards.1.cell_at(x, y) {
+                        self.my_shot = Point::new(c.x, c.y);
+                        self.fire(audio).await;
+                    }
+                }
+
                 _ => {}
             }
         }
@@ -386,54 +618,350 @@ impl state::State for BattleState {
         next_state.replace(state::NextState::Continue);
     }
 
-    async fn draw(&self, canvas: &mut Canvas<Window>) {
-        let x_offset: i32 = (config::WINDOW_WIDTH as i32 - config::WINDOW_HEIGHT as i32) / 2;
-        let min_wh: i32 = std::cmp::min(config::WINDOW_WIDTH as i32, config::WINDOW_HEIGHT as i32);
-
-        let x_interval: i32 = min_wh / 10;
-        let y_interval: i32 = min_wh / 10;
-
-        // draw board lines.
-        canvas.set_draw_color(Color::RGBA(0, 255, 0, 255));
+    async fn draw(
+        &mut self,
+        canvas: &mut Canvas<Window>,
+        text: &text::Text,
+        theme: &theme::Theme,
+        dt: Duration,
+    ) {
+        let my_board = self.my_board.lock().await;
+        let my_targets = self.my_targets.lock().await;
+        draw_boards(
+            canvas,
+            text,
+            theme,
+            &self.boards,
+            &my_board,
+            &my_targets,
+            &self.fleet,
+        );
+        drop(my_board);
+        drop(my_targets);
 
-        for (p1, p2) in self.board_lines.iter() {
-            canvas.draw_line(*p1, *p2).unwrap()
-        }
+        self.animations.draw(canvas, theme, dt);
 
-        // draw all cached shots
-        self.draw_shots(
-            canvas,
-            Color::RGBA(0, 0, 255, 30),
-            &self.opponent_miss_shots,
-        )
-        .await;
-        self.draw_shots(canvas, Color::RGBA(255, 0, 0, 30), &self.opponent_hit_shots)
-            .await;
-        self.draw_shots(canvas, Color::RGBA(0, 0, 255, 255), &self.my_miss_shots)
-            .await;
-        self.draw_shots(canvas, Color::RGBA(255, 0, 0, 255), &self.my_hit_shots)
-            .await;
-
-        // draw my shot.
+        // draw my shot and whose turn it is.
         let is_send_shot = self.is_send_shot.clone();
         let is_send_shot = is_send_shot.lock().await;
 
+        let turn_center = self.layout.bottom_center(config::BOARD_MARGIN as i32 / 2);
+
         if *is_send_shot {
-            canvas.set_draw_color(Color::RGBA(0, 255, 0, 150));
+            text.draw_centered(
+                canvas,
+                text::Size::Normal,
+                "your turn",
+                turn_center,
+                Color::RGBA(0, 255, 0, 255),
+            );
+
+            self.turn_time += dt;
+            let pulse = animation::pulse(self.turn_time, PULSE_PERIOD);
+            canvas.set_draw_color(Color::RGBA(0, 255, 0, 90 + (120.0 * pulse) as u8));
         } else {
+            self.turn_time = Duration::from_secs(0);
+
+            text.draw_centered(
+                canvas,
+                text::Size::Normal,
+                "opponent's turn",
+                turn_center,
+                Color::RGBA(255, 255, 255, 150),
+            );
             canvas.set_draw_color(Color::RGBA(255, 255, 255, 50));
         }
 
         drop(is_send_shot);
 
-        let rect = Rect::new(
-            self.my_shot.x * x_interval + x_offset,
-            self.my_shot.y * y_interval,
-            x_interval as u32,
-            y_interval as u32,
-        );
+        let rect = self
+            .boards
+            .1
+            .cell_rect(engine::Coord::new(self.my_shot.x, self.my_shot.y));
 
         canvas.fill_rect(rect).unwrap();
         canvas.draw_rect(rect).unwrap();
+
+        if *self.is_disconnected.lock().await {
+            canvas.set_draw_color(Color::RGBA(0, 0, 0, 200));
+            canvas
+                .fill_rect(Rect::new(
+                    0,
+                    self.layout.height / 2 - 40,
+                    self.layout.width as u32,
+                    80,
+                ))
+                .unwrap();
+
+            text.draw_centered(
+                canvas,
+                text::Size::Normal,
+                "opponent disconnected, waiting for them to rejoin",
+                self.layout.center().offset(0, -12),
+                Color::RGBA(255, 80, 80, 255),
+            );
+            text.draw_centered(
+                canvas,
+                text::Size::Small,
+                "BACKSPACE to give up and go back to the lobby",
+                self.layout.center().offset(0, 16),
+                Color::RGBA(255, 255, 255, 255),
+            );
+        }
+
+        if let Some(chat_panel) = self.session.chat_panel.as_ref() {
+            chat_panel.draw(canvas, text).await;
+        }
+    }
+
+    fn relayout(&mut self, layout: &layout::Layout) {
+        self.layout = *layout;
+        self.boards = geometry::BoardGeometry::side_by_side(
+            layout,
+            self.boards.0.width,
+            self.boards.0.height,
+        );
+
+        // the shots in flight were placed for the old boards.
+        self.animations.clear();
+
+        if let Some(chat_panel) = self.session.chat_panel.as_mut() {
+            chat_panel.relayout(layout);
+        }
+    }
+
+    // the crosshair pulses all through our turn.
+    fn is_animating(&self) -> bool {
+        self.animations.is_running() || self.turn_time > Duration::from_secs(0)
+    }
+}
+
+// both boards of a match: my fleet with the opponent's shots at it on the
+// left, my shots at theirs on the right. shared with 'ReplayState'.
+pub fn draw_boards(
+    canvas: &mut Canvas<Window>,
+    text: &text::Text,
+    theme: &theme::Theme,
+    boards: &(geometry::BoardGeometry, geometry::BoardGeometry),
+    my_board: &engine::Board,
+    my_targets: &engine::TargetBoard,
+    fleet: &[engine::ShipType],
+) {
+    let (fleet_board, target_board) = boards;
+
+    for (board, title) in [(fleet_board, "your fleet"), (target_board, "your shots")].iter() {
+        theme.draw_water(canvas, board);
+
+        // draw board lines.
+        canvas.set_draw_color(Color::RGBA(0, 255, 0, 255));
+        for (p1, p2) in board.lines().iter() {
+            canvas.draw_line(*p1, *p2).unwrap()
+        }
+
+        text.draw_board_labels(
+            canvas,
+            board.origin,
+            board.interval,
+            board.width,
+            board.height,
+            Color::RGBA(0, 255, 0, 255),
+        );
+
+        text.draw_centered(
+            canvas,
+            text::Size::Normal,
+            title,
+            Point::new(board.body().center().x(), board.origin.y - 45),
+            Color::RGBA(255, 255, 255, 255),
+        );
+    }
+
+    // my fleet under the opponent's shots.
+    for ship in my_board.ships().iter() {
+        theme.draw_ship(canvas, fleet_board, ship.cells());
+    }
+    theme.draw_markers(canvas, fleet_board, theme::Sprite::Miss, my_board.misses());
+    theme.draw_markers(canvas, fleet_board, theme::Sprite::Hit, my_board.hits());
+
+    theme.draw_markers(
+        canvas,
+        target_board,
+        theme::Sprite::Miss,
+        my_targets.misses(),
+    );
+    theme.draw_markers(canvas, target_board, theme::Sprite::Hit, my_targets.hits());
+
+    // both fleets' status between the boards, each next to its own.
+    let is_sunk: Vec<bool> = my_board.ships().iter().map(|ship| ship.is_sunk()).collect();
+    fleet_panel::draw(
+        canvas,
+        fleet_board.body().right() + 16,
+        fleet_board.origin.y,
+        fleet,
+        &is_sunk,
+        Color::RGBA(0, 255, 0, 200),
+    );
+
+    fleet_panel::draw(
+        canvas,
+        target_board.origin.x - 16 - fleet_panel::width(fleet),
+        target_board.origin.y,
+        fleet,
+        &fleet_panel::sunk_flags(fleet, my_targets.sunk()),
+        Color::RGBA(255, 0, 0, 200),
+    );
+}
+
+// next game message from the opponent, skipping handshake leftovers,
+// pings and frames that don't decode. a silent opponent is flagged as
+// disconnected and waited for, if they come back they get the match so
+// far and our unanswered shot again.
+async fn next_message(
+    peer: &mut dyn peer::Peer,
+    replay: &Mutex<replay::Replay>,
+    pending_shot: Option<engine::Coord>,
+    is_disconnected: &Mutex<bool>,
+    resigned: &Notify,
+) -> mini_redis::Result<protocol::Message> {
+    loop {
+        let received = tokio::select! {
+            received = tokio::time::timeout(config::DISCONNECT_TIMEOUT, peer.receive()) => received,
+            // we're leaving, the peer is needed to say so.
+            _ = resigned.notified() => return Err("we resigned".into()),
+        };
+
+        let msg = match received {
+            Ok(msg) => msg?,
+            Err(_) => {
+                let mut is_disconnected = is_disconnected.lock().await;
+                if !*is_disconnected {
+                    println!("<BattleState> opponent disconnected");
+                    *is_disconnected = true;
+                }
+                continue;
+            }
+        };
+
+        let msg = match msg {
+            Some(msg) => msg,
+            None => return Err("connection closed".into()),
+        };
+
+        *is_disconnected.lock().await = false;
+
+        match protocol::Message::decode(&msg) {
+            Ok(protocol::Message::Hello { .. }) | Ok(protocol::Message::Ping) => {}
+
+            // chat lines have their own channel, one here isn't a move either.
+            Ok(protocol::Message::Chat(_)) => {}
+
+            Ok(protocol::Message::Resume) => {
+                println!("<BattleState> opponent is back, sending the match so far");
+
+                let history: Vec<replay::Event> = replay
+                    .lock()
+                    .await
+                    .events
+                    .iter()
+                    .filter(|event| !matches!(event, replay::Event::Place(..)))
+                    .cloned()
+                    .collect();
+                peer.send_message(&protocol::Message::History(history))
+                    .await?;
+
+                if let Some(shot) = pending_shot {
+                    peer.send_message(&protocol::Message::Shot(shot)).await?;
+                }
+            }
+
+            Ok(protocol::Message::Commit(digest)) => {
+                let mut replay = replay.lock().await;
+
+                // a commitment after the first shot could be fitted to the answers.
+                let is_started = replay.events.iter().any(|event| {
+                    matches!(event, replay::Event::Shot(..) | replay::Event::Incoming(..))
+                });
+
+                if replay.their_commitment.is_none() && !is_started {
+                    replay.their_commitment = Some(digest);
+                    peer.save_match(replay.encode().into()).await?;
+                } else {
+                    println!("<BattleState> ignoring a late commitment");
+                }
+            }
+
+            Ok(msg) => return Ok(msg),
+
+            Err(err) => println!("<BattleState> protocol error: {}", err),
+        }
+    }
+}
+
+// once the match is over both sides reveal their fleet and check the other's
+// answers against it. an opponent who owes us their fleet, having won or lost
+// by our count, forfeits without it.
+async fn exchange_reveals(
+    peer: &mut dyn peer::Peer,
+    replay: &Mutex<replay::Replay>,
+    is_disconnected: &Mutex<bool>,
+    resigned: &Notify,
+    is_reveal_owed: bool,
+) -> commitment::Verdict {
+    let reveal = {
+        let replay = replay.lock().await;
+
+        let fleet: commitment::Fleet = replay
+            .events
+            .iter()
+            .filter_map(|event| match event {
+                replay::Event::Place(ship_type, cells) => Some((*ship_type, cells.clone())),
+                _ => None,
+            })
+            .collect();
+
+        replay
+            .commitment
+            .as_ref()
+            .map(|commitment| protocol::Message::Reveal(commitment.salt.clone(), fleet))
+    };
+
+    if let Some(reveal) = reveal {
+        if let Err(err) = peer.send_message(&reveal).await {
+            println!("<BattleState> couldn't reveal our fleet: {}", err);
+        }
+    }
+
+    let theirs = tokio::time::timeout(
+        config::DISCONNECT_TIMEOUT,
+        next_reveal(peer, replay, is_disconnected, resigned),
+    )
+    .await;
+
+    match theirs {
+        Ok(Ok((salt, fleet))) => match commitment::verify(&*replay.lock().await, &salt, &fleet) {
+            Ok(()) => commitment::Verdict::Verified,
+            Err(cheat) => {
+                println!("<BattleState> the opponent cheated: {}", cheat);
+                commitment::Verdict::Forfeit(cheat)
+            }
+        },
+
+        _ if is_reveal_owed => commitment::Verdict::Forfeit(commitment::Cheat::NoReveal),
+
+        _ => commitment::Verdict::Unverified,
+    }
+}
+
+async fn next_reveal(
+    peer: &mut dyn peer::Peer,
+    replay: &Mutex<replay::Replay>,
+    is_disconnected: &Mutex<bool>,
+    resigned: &Notify,
+) -> mini_redis::Result<(String, commitment::Fleet)> {
+    loop {
+        match next_message(peer, replay, None, is_disconnected, resigned).await? {
+            protocol::Message::Reveal(salt, fleet) => return Ok((salt, fleet)),
+            other => println!("<BattleState> unexpected message: {:?}", other),
+        }
     }
 }
@@ -0,0 +1,229 @@
+// a match server without a window. it runs the relay the desktop clients
+// talk through, pairs up the players queueing on it and referees every
+// match with its own copy of the rules.
+//
+//     battleship-server [address]
+
+use mini_redis::{client, server};
+use std::time::{Duration, Instant};
+use tokio::net::TcpListener;
+
+use rusty_battleship::commitment::Cheat;
+use rusty_battleship::engine::Side;
+use rusty_battleship::protocol::{self, Message};
+use rusty_battleship::referee::{Foul, Referee};
+use rusty_battleship::settings::MatchSettings;
+
+const DEFAULT_ADDRESS: &str = "0.0.0.0:3232";
+
+// players send heartbeats, a match this quiet has been left by both.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+// a player whose fleet was hit all over has this long to reveal it.
+const REVEAL_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[tokio::main]
+pub async fn main() -> mini_redis::Result<()> {
+    let address = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| DEFAULT_ADDRESS.to_string());
+
+    let listener = TcpListener::bind(address.as_str()).await?;
+    let addr = listener.local_addr()?;
+    println!("<Server> listening at {}", addr);
+
+    tokio::spawn(async move { server::run(listener, tokio::signal::ctrl_c()).await });
+
+    // the lobby and the referees talk to the relay like any player.
+    lobby(format!("127.0.0.1:{}", addr.port())).await
+}
+
+// pairs players in the order they queue, the first of a pair shoots first.
+async fn lobby(relay: String) -> mini_redis::Result<()> {
+    let mut client = client::connect(relay.as_str()).await?;
+    let mut subscriber = client::connect(relay.as_str())
+        .await?
+        .subscribe(vec![protocol::LOBBY_CHANNEL.to_string()])
+        .await?;
+
+    let mut waiting: Option<String> = None;
+
+    while let Some(msg) = subscriber.next_message().await? {
+        let player = match Message::decode(&msg.content) {
+            Ok(Message::Queue(player)) => player,
+
+            Ok(other) => {
+                println!("<Server> unexpected message: {:?}", other);
+                continue;
+            }
+
+            Err(err) => {
+                println!("<Server> protocol error: {}", err);
+                continue;
+            }
+        };
+
+        println!("<Server> {} queued", player);
+
+        // whoever waited may have given up in the meantime.
+        let first = match waiting.take() {
+            Some(first) if first != player => first,
+            _ => {
+                waiting = Some(player);
+                continue;
+            }
+        };
+        let listeners = client
+            .publish(&protocol::inbox(&first), Message::Ping.encode())
+            .await?;
+        if listeners == 0 {
+            println!("<Server> {} left the queue", first);
+            waiting = Some(player);
+            continue;
+        }
+
+        let relay = relay.clone();
+        tokio::spawn(async move {
+            println!("<Server> {} plays {}", first, player);
+
+            if let Err(err) = referee(relay, [first.clone(), player.clone()]).await {
+                println!("<Server> match of {} and {} failed: {}", first, player, err);
+            }
+        });
+    }
+
+    Ok(())
+}
+
+// seats both players, then passes on every message that keeps to the rules.
+// a player who breaks them, or loses and doesn't reveal their fleet in time,
+// loses the match as if they resigned. once both ask for a rematch they play
+// again, the second player shooting first.
+async fn referee(relay: String, mut players: [String; 2]) -> mini_redis::Result<()> {
+    let settings = MatchSettings::default();
+
+    let mut client = client::connect(relay.as_str()).await?;
+    let mut subscriber = client::connect(relay.as_str())
+        .await?
+        .subscribe(
+            players
+                .iter()
+                .flat_map(|player| {
+                    let outbox = protocol::outbox(player);
+                    vec![protocol::talk_channel(&outbox), outbox]
+                })
+                .collect(),
+        )
+        .await?;
+
+    loop {
+        for (player, side) in players.iter().zip([Side::First, Side::Second].iter()) {
+            let inbox = protocol::inbox(player);
+            client
+                .publish(&inbox, Message::Seat(*side).encode())
+                .await?;
+            client
+                .publish(&inbox, Message::hello(Some(settings.clone())).encode())
+                .await?;
+        }
+
+        let mut referee = Referee::new(settings.clone());
+        let mut is_rematch = [false, false];
+        // set once someone owes us their reveal.
+        let mut reveal_deadline: Option<Instant> = None;
+
+        while is_rematch != [true, true] {
+            // a loser who doesn't show their fleet in time forfeits.
+            let wait = match (referee.owes_reveal(), reveal_deadline) {
+                (Some(side), Some(deadline)) if Instant::now() >= deadline => {
+                    let foul = Foul::Cheated(Cheat::NoReveal);
+                    return forfeit(&mut client, &players, side, foul).await;
+                }
+                (Some(_), Some(deadline)) => deadline.saturating_duration_since(Instant::now()),
+                _ => IDLE_TIMEOUT,
+            };
+
+            let msg = match tokio::time::timeout(wait, subscriber.next_message()).await {
+                Ok(msg) => msg?,
+                Err(_) if referee.owes_reveal().is_some() => continue,
+                Err(_) if referee.is_done() => return Ok(()),
+                Err(_) => return Err("both players went quiet".into()),
+            };
+            let msg = match msg {
+                Some(msg) => msg,
+                None => return Ok(()),
+            };
+
+            let (side, to) = if msg.channel.starts_with(&protocol::outbox(&players[0])) {
+                (Side::First, &players[1])
+            } else {
+                (Side::Second, &players[0])
+            };
+
+            // chat lines aren't moves, they pass unchecked.
+            if msg.channel == protocol::talk_channel(&protocol::outbox(&players[side.index()])) {
+                client
+                    .publish(&protocol::talk_channel(&protocol::inbox(to)), msg.content)
+                    .await?;
+                continue;
+            }
+
+            match Message::decode(&msg.content) {
+                // the settings are ours to decide.
+                Ok(Message::Hello { .. }) => continue,
+
+                // only sent once the match is over.
+                Ok(Message::Rematch) => is_rematch[side.index()] = true,
+
+                Ok(message) => match referee.check(side, &message) {
+                    Ok(()) => {}
+
+                    // the other player checks the reveal too and sees it for themselves.
+                    Err(Foul::Cheated(cheat)) => {
+                        println!("<Server> {:?} player cheated: {}", side, cheat);
+                    }
+
+                    Err(foul) => return forfeit(&mut client, &players, side, foul).await,
+                },
+
+                Err(err) => {
+                    println!("<Server> protocol error: {}", err);
+                    continue;
+                }
+            }
+
+            client.publish(&protocol::inbox(to), msg.content).await?;
+
+            if reveal_deadline.is_none() && referee.owes_reveal().is_some() {
+                reveal_deadline = Some(Instant::now() + REVEAL_TIMEOUT);
+            }
+        }
+
+        println!("<Server> {} and {} play again", players[0], players[1]);
+        players.swap(0, 1);
+    }
+}
+
+// ends the match for a player who broke the rules and tells them why, their
+// opponent wins as if they resigned.
+async fn forfeit(
+    client: &mut client::Client,
+    players: &[String; 2],
+    side: Side,
+    foul: Foul,
+) -> mini_redis::Result<()> {
+    println!("<Server> {:?} player forfeits: {}", side, foul);
+
+    client
+        .publish(
+            &protocol::inbox(&players[side.index()]),
+            Message::Foul(foul.to_string()).encode(),
+        )
+        .await?;
+    client
+        .publish(
+            &protocol::inbox(&players[side.other().index()]),
+            Message::Resign.encode(),
+        )
+        .await?;
+    Ok(())
+}
@@ -1,11 +1,27 @@
+use async_trait::async_trait;
 use bytes::Bytes;
 use mini_redis::{client, Result};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Weak};
+use tokio::sync::Mutex;
+
+use super::config;
+use super::peer;
+use super::protocol;
+use super::room;
+use super::wake;
 // use std::io;
 
 pub struct Chat {
-    client: client::Client,
+    // shared with the heartbeat task, which stops once the chat is dropped.
+    client: Arc<Mutex<client::Client>>,
     receive_channel: client::Subscriber,
     send_channel_key: String,
+    // our side of a running match, see 'peer::Peer::save_match'. it holds
+    // our fleet, so it never goes to the server.
+    match_path: PathBuf,
 }
 
 impl Chat {
@@ -15,23 +31,244 @@ impl Chat {
         send_channel_key: String,
     ) -> Result<Chat> {
         let client = client::connect(addr.as_str()).await?;
-        let subscriber = client.subscribe(vec![receive_channel_key.into()]).await?;
+        let subscriber = client.subscribe(vec![receive_channel_key.clone()]).await?;
 
-        Ok(Chat {
-            client: client::connect(addr.as_str()).await?,
+        let chat = Chat {
+            client: Arc::new(Mutex::new(client::connect(addr.as_str()).await?)),
             receive_channel: subscriber,
             send_channel_key: send_channel_key,
-        })
+            match_path: match_path(&addr, &receive_channel_key),
+        };
+
+        heartbeat(Arc::downgrade(&chat.client), chat.send_channel_key.clone());
+
+        Ok(chat)
     }
 
     pub async fn receive(&mut self) -> Result<Option<client::Message>> {
-        Ok(self.receive_channel.next_message().await?)
+        let msg = self.receive_channel.next_message().await?;
+        wake::wake();
+        Ok(msg)
     }
 
     pub async fn send(&mut self, msg: Bytes) -> Result<()> {
+        let mut client = self.client.lock().await;
+        client
+            .publish(self.send_channel_key.as_str(), msg.clone())
+            .await?;
+        // spectators, if any, get a copy. heartbeats aren't worth one.
+        client
+            .publish(&room::watch_channel(&self.send_channel_key), msg)
+            .await?;
+        Ok(())
+    }
+
+    // publishes on another channel than the opponent's, e.g. to queue on a match server.
+    pub async fn announce(&mut self, channel: &str, msg: Bytes) -> Result<()> {
+        self.client.lock().await.publish(channel, msg).await?;
+        Ok(())
+    }
+}
+
+// whether we dropped out of a match at 'addr' listening on
+// 'receive_channel_key' recently enough to resume it.
+pub fn has_dropped_match(addr: &str, receive_channel_key: &str) -> bool {
+    fs::metadata(match_path(addr, receive_channel_key))
+        .and_then(|meta| meta.modified())
+        .map(|modified| modified.elapsed().unwrap_or_default() <= config::RESUME_EXPIRY)
+        .unwrap_or(false)
+}
+
+// "<dir>/<addr>-<channel>.match", one per server and seat.
+fn match_path(addr: &str, receive_channel_key: &str) -> PathBuf {
+    let name: String = format!("{}-{}", addr, receive_channel_key)
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+
+    Path::new(config::RESUME_DIR).join(format!("{}.match", name))
+}
+
+// a spectator's line to a room. it only ever subscribes, and a subscribed
+// connection can't publish, so spectators can't talk to the players.
+pub struct Watch {
+    receive_channel: client::Subscriber,
+}
+
+impl Watch {
+    pub async fn new(addr: String, channel_keys: Vec<String>) -> Result<Watch> {
+        let watch_keys = channel_keys
+            .iter()
+            .map(|key| room::watch_channel(key))
+            .collect();
+        let subscriber = client::connect(addr.as_str())
+            .await?
+            .subscribe(watch_keys)
+            .await?;
+
+        Ok(Watch {
+            receive_channel: subscriber,
+        })
+    }
+
+    // a message on one of the watch channels, see 'room::watch_channel'.
+    pub async fn receive(&mut self) -> Result<Option<client::Message>> {
+        let msg = self.receive_channel.next_message().await;
+        wake::wake();
+        msg
+    }
+}
+
+pub struct Line {
+    pub is_mine: bool,
+    pub text: String,
+}
+
+// chat lines between the players. they have channels and a subscription of
+// their own, see 'protocol::talk_channel', and only 'Chat' is read from them.
+#[derive(Clone)]
+pub struct Talk {
+    client: Arc<Mutex<client::Client>>,
+    send_channel_key: String,
+    // shared with the listening task, which stops once the log is dropped.
+    pub log: Arc<Mutex<Vec<Line>>>,
+}
+
+impl Talk {
+    pub async fn new(
+        addr: String,
+        receive_channel_key: &str,
+        send_channel_key: &str,
+    ) -> Result<Talk> {
+        let subscriber = client::connect(addr.as_str())
+            .await?
+            .subscribe(vec![protocol::talk_channel(receive_channel_key)])
+            .await?;
+
+        let talk = Talk {
+            client: Arc::new(Mutex::new(client::connect(addr.as_str()).await?)),
+            send_channel_key: protocol::talk_channel(send_channel_key),
+            log: Arc::new(Mutex::new(Vec::new())),
+        };
+
+        listen(subscriber, Arc::downgrade(&talk.log));
+
+        Ok(talk)
+    }
+
+    pub async fn say(&self, text: String) -> Result<()> {
         self.client
-            .publish(self.send_channel_key.as_str(), msg)
+            .lock()
+            .await
+            .publish(
+                self.send_channel_key.as_str(),
+                protocol::Message::Chat(text.clone()).encode(),
+            )
             .await?;
+
+        self.log.lock().await.push(Line {
+            is_mine: true,
+            text: text,
+        });
+        Ok(())
+    }
+}
+
+fn listen(mut subscriber: client::Subscriber, log: Weak<Mutex<Vec<Line>>>) {
+    tokio::spawn(async move {
+        loop {
+            let msg = match subscriber.next_message().await {
+                Ok(Some(msg)) => {
+                    wake::wake();
+                    msg
+                }
+                Ok(None) => return,
+                Err(err) => {
+                    println!("<Chat> stopped listening: {}", err);
+                    return;
+                }
+            };
+
+            let log = match log.upgrade() {
+                Some(log) => log,
+                None => return,
+            };
+
+            match protocol::Message::decode(&msg.content) {
+                Ok(protocol::Message::Chat(text)) => log.lock().await.push(Line {
+                    is_mine: false,
+                    text: text,
+                }),
+
+                Ok(other) => println!("<Chat> not a chat line: {:?}", other),
+
+                Err(err) => println!("<Chat> protocol error: {}", err),
+            }
+        }
+    });
+}
+
+// pings the opponent so they can tell a slow turn from a closed window.
+fn heartbeat(client: Weak<Mutex<client::Client>>, channel_key: String) {
+    tokio::spawn(async move {
+        let ping = protocol::Message::Ping.encode();
+
+        loop {
+            tokio::time::delay_for(config::HEARTBEAT_INTERVAL).await;
+
+            let client = match client.upgrade() {
+                Some(client) => client,
+                None => return,
+            };
+
+            let mut client = client.lock().await;
+            if let Err(err) = client.publish(channel_key.as_str(), ping.clone()).await {
+                println!("<Chat> heartbeat stopped: {}", err);
+                return;
+            }
+        }
+    });
+}
+
+#[async_trait]
+impl peer::Peer for Chat {
+    async fn send(&mut self, frame: Bytes) -> Result<()> {
+        Chat::send(self, frame).await
+    }
+
+    async fn receive(&mut self) -> Result<Option<Bytes>> {
+        Ok(Chat::receive(self).await?.map(|msg| msg.content))
+    }
+
+    async fn save_match(&mut self, state: Bytes) -> Result<()> {
+        if state.is_empty() {
+            return match fs::remove_file(&self.match_path) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err.into()),
+                _ => Ok(()),
+            };
+        }
+
+        if let Some(dir) = self.match_path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.match_path, state)?;
         Ok(())
     }
+
+    async fn load_match(&mut self) -> Result<Option<Bytes>> {
+        let age = match fs::metadata(&self.match_path).and_then(|meta| meta.modified()) {
+            Ok(modified) => modified.elapsed().unwrap_or_default(),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+
+        // the opponent stopped waiting for us long ago.
+        if age > config::RESUME_EXPIRY {
+            fs::remove_file(&self.match_path)?;
+            return Ok(None);
+        }
+
+        let state = fs::read(&self.match_path)?;
+        Ok(Some(Bytes::from(state)).filter(|state| !state.is_empty()))
+    }
 }
@@ -0,0 +1,159 @@
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+
+use super::chat;
+use super::layout;
+use super::text;
+use super::text_field;
+
+// lines shown at once, older ones scroll away.
+const VISIBLE_LINES: usize = 8;
+const LINE_HEIGHT: i32 = 20;
+const PANEL_HEIGHT: u32 = 230;
+
+// the chat overlay over a match: what both players said and a field to
+// type in. TAB opens and closes it, while it's open it takes every key.
+pub struct ChatPanel {
+    talk: chat::Talk,
+    opponent_name: String,
+    field: text_field::TextField,
+    // lines in the log when it was last closed, the rest are new.
+    seen: usize,
+    // the panel sits at the bottom, as wide as the window.
+    layout: layout::Layout,
+}
+
+impl ChatPanel {
+    pub fn new(talk: chat::Talk, opponent_name: String) -> ChatPanel {
+        let mut field = text_field::TextField::new(Rect::new(0, 0, 1, 1), String::new());
+        field.allows_spaces = true;
+
+        let mut chat_panel = ChatPanel {
+            talk: talk,
+            opponent_name: opponent_name,
+            field: field,
+            seen: 0,
+            layout: layout::Layout::default(),
+        };
+        chat_panel.relayout(&layout::Layout::default());
+
+        chat_panel
+    }
+
+    pub fn relayout(&mut self, layout: &layout::Layout) {
+        self.layout = *layout;
+        self.field.body = Rect::new(
+            10,
+            self.top() + PANEL_HEIGHT as i32 - 50,
+            std::cmp::max(layout.width - 20, 1) as u32,
+            40,
+        );
+    }
+
+    fn top(&self) -> i32 {
+        self.layout.height - PANEL_HEIGHT as i32
+    }
+
+    // returns whether the event was meant for the panel.
+    pub async fn handle_event(&mut self, event: &Event) -> bool {
+        match event {
+            Event::KeyDown {
+                keycode: Some(Keycode::Tab),
+                ..
+            } => {
+                self.toggle().await;
+                return true;
+            }
+
+            _ if !self.field.is_focused => return false,
+
+            _ if self.field.handle_event(event) => return true,
+
+            Event::KeyDown {
+                keycode: Some(Keycode::Return),
+                ..
+            } => {
+                let line = self.field.value.trim().to_string();
+                self.field.value.clear();
+
+                if !line.is_empty() {
+                    let talk = self.talk.clone();
+                    tokio::spawn(async move {
+                        if let Err(err) = talk.say(line).await {
+                            println!("<ChatPanel> couldn't send: {}", err);
+                        }
+                    });
+                }
+                return true;
+            }
+
+            Event::KeyDown {
+                keycode: Some(Keycode::Escape),
+                ..
+            } => {
+                self.toggle().await;
+                return true;
+            }
+
+            _ => return false,
+        }
+    }
+
+    async fn toggle(&mut self) {
+        if self.field.is_focused {
+            self.seen = self.talk.log.lock().await.len();
+        }
+        self.field.is_focused = !self.field.is_focused;
+    }
+
+    pub async fn draw(&self, canvas: &mut Canvas<Window>, text: &text::Text) {
+        let log = self.talk.log.lock().await;
+
+        if !self.field.is_focused {
+            let hint = match log.len() - self.seen {
+                0 => "TAB to chat".to_string(),
+                new => format!("TAB to chat ({} new)", new),
+            };
+            text.draw(
+                canvas,
+                text::Size::Small,
+                &hint,
+                10,
+                self.layout.height - 25,
+                Color::RGBA(255, 255, 255, 150),
+            );
+            return;
+        }
+
+        let top = self.top();
+
+        canvas.set_draw_color(Color::RGBA(0, 0, 0, 220));
+        canvas
+            .fill_rect(Rect::new(0, top, self.layout.width as u32, PANEL_HEIGHT))
+            .unwrap();
+
+        let first = log.len().saturating_sub(VISIBLE_LINES);
+        for (i, line) in log[first..].iter().enumerate() {
+            let (name, color) = if line.is_mine {
+                ("you", Color::RGBA(0, 255, 0, 255))
+            } else {
+                (self.opponent_name.as_str(), Color::RGBA(255, 255, 255, 255))
+            };
+
+            text.draw(
+                canvas,
+                text::Size::Small,
+                &format!("{}: {}", name, line.text),
+                10,
+                top + 8 + i as i32 * LINE_HEIGHT,
+                color,
+            );
+        }
+
+        self.field.draw(canvas, text);
+    }
+}
@@ -1,6 +1,7 @@
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
 use sdl2::pixels::Color;
+use sdl2::rect::Point;
 use sdl2::rect::Rect;
 use sdl2::render::Canvas;
 use sdl2::video::Window;
@@ -9,27 +10,55 @@ use sdl2::EventPump;
 use async_trait::async_trait;
 use mini_redis::server;
 use std::net::SocketAddr;
+use std::time::Duration;
 use tokio::net::TcpListener;
 use tokio::task::JoinHandle;
 
+use super::ai;
+use super::audio;
 use super::config;
+use super::error_state;
+use super::fleet_panel;
+use super::geometry;
 use super::initial_state;
+use super::layout;
+use super::lobby_state;
+use super::prefs;
+use super::registry;
+use super::replay;
+use super::replay_state;
+use super::settings;
+use super::spectate_state;
 use super::state;
+use super::text;
+use super::text_field;
+use super::theme;
 
-struct Button {
-    body: Rect,
-    color: Color,
-    text: String,
+// side of the board preview next to the buttons.
+const PREVIEW_LENGTH: i32 = 120;
+
+pub struct Button {
+    pub body: Rect,
+    pub color: Color,
+    pub text: String,
 }
 
 impl Button {
-    fn draw(&self, canvas: &mut Canvas<Window>) {
+    pub fn draw(&self, canvas: &mut Canvas<Window>, text: &text::Text) {
         canvas.set_draw_color(self.color);
         canvas.fill_rect(Some(self.body)).unwrap();
         canvas.draw_rect(self.body).unwrap();
+
+        text.draw_centered(
+            canvas,
+            text::Size::Small,
+            &self.text,
+            self.body.center(),
+            Color::RGBA(0, 0, 0, 255),
+        );
     }
 
-    fn is_click(&self, x: i32, y: i32) -> bool {
+    pub fn is_click(&self, x: i32, y: i32) -> bool {
         if x >= self.body.x()
             && x < self.body.x() + self.body.width() as i32
             && y >= self.body.y()
@@ -41,55 +70,471 @@ impl Button {
     }
 }
 
+// what a button or field is for, just above it.
+pub fn draw_caption(canvas: &mut Canvas<Window>, text: &text::Text, body: Rect, caption: &str) {
+    text.draw(
+        canvas,
+        text::Size::Small,
+        caption,
+        body.x(),
+        body.y() - 18,
+        Color::RGBA(255, 255, 255, 255),
+    );
+}
+
+// the host connects to its own server, through loopback when it listens everywhere.
+fn local_address(addr: SocketAddr) -> String {
+    if addr.ip().is_unspecified() {
+        return format!("127.0.0.1:{}", addr.port());
+    }
+    return addr.to_string();
+}
+
 pub struct ChooseState {
+    layout: layout::Layout,
+    host_field: text_field::TextField,
     create_button: Button,
-    join_button: Button,
+    join_field: text_field::TextField,
+    rooms_button: Button,
+    // the join address may also be a match server, see 'battleship-server'.
+    queue_button: Button,
+    computer_buttons: Vec<(Button, ai::Difficulty)>,
+    // clicking the preview cycles through the presets.
+    settings_button: Button,
+    presets: Vec<settings::MatchSettings>,
+    settings_index: usize,
+
+    // sound choices, saved right away.
+    prefs: prefs::Prefs,
+    volume_button: Button,
+    mute_button: Button,
+    music_button: Button,
+
+    // why the typed address can't be used.
+    error: Option<String>,
 }
 
 impl ChooseState {
     pub fn new() -> ChooseState {
-        ChooseState {
+        let prefs = prefs::Prefs::load(std::path::Path::new(config::PREFS_FILE));
+
+        let mut state = ChooseState {
+            layout: layout::Layout::default(),
+
+            host_field: text_field::TextField::new(
+                Rect::new(0, 0, 180, 40),
+                prefs.host_address.clone(),
+            ),
+
             create_button: Button {
-                // placeholders
-                body: Rect::new(
-                    config::WINDOW_WIDTH as i32 / 2 - 100,
-                    config::WINDOW_HEIGHT as i32 / 3 - 20,
-                    200,
-                    40,
-                ),
+                body: Rect::new(0, 0, 110, 40),
 
                 color: Color::RGBA(0, 0, 255, 255),
 
-                text: String::from("127.0.0.1:3232"),
+                text: String::from("host"),
             },
 
-            join_button: Button {
-                // placeholders
-                body: Rect::new(
-                    config::WINDOW_WIDTH as i32 / 2 - 100,
-                    config::WINDOW_HEIGHT as i32 / 2 - 20,
-                    200,
-                    40,
-                ),
+            join_field: text_field::TextField::new(
+                Rect::new(0, 0, 180, 40),
+                prefs.join_address.clone(),
+            ),
+
+            rooms_button: Button {
+                body: Rect::new(0, 0, 52, 40),
 
                 color: Color::RGBA(0, 255, 0, 255),
 
-                text: String::from("127.0.0.1:3232"),
+                text: String::from("rooms"),
+            },
+
+            queue_button: Button {
+                body: Rect::new(0, 0, 52, 40),
+
+                color: Color::RGBA(0, 200, 150, 255),
+
+                text: String::from("queue"),
+            },
+
+            // one button per difficulty, easiest on the left.
+            computer_buttons: vec![
+                (
+                    Button {
+                        body: Rect::new(0, 0, 60, 40),
+
+                        color: Color::RGBA(255, 150, 150, 255),
+
+                        text: String::from("easy"),
+                    },
+                    ai::Difficulty::Easy,
+                ),
+                (
+                    Button {
+                        body: Rect::new(0, 0, 60, 40),
+
+                        color: Color::RGBA(255, 75, 75, 255),
+
+                        text: String::from("medium"),
+                    },
+                    ai::Difficulty::Medium,
+                ),
+                (
+                    Button {
+                        body: Rect::new(0, 0, 60, 40),
+
+                        color: Color::RGBA(255, 0, 0, 255),
+
+                        text: String::from("hard"),
+                    },
+                    ai::Difficulty::Hard,
+                ),
+            ],
+
+            settings_button: Button {
+                body: Rect::new(0, 0, PREVIEW_LENGTH as u32, PREVIEW_LENGTH as u32),
+
+                color: Color::RGBA(40, 40, 40, 255),
+
+                text: String::from("board"),
+            },
+
+            presets: settings::MatchSettings::presets(),
+            settings_index: 0,
+
+            volume_button: Button {
+                body: Rect::new(10, 0, 80, 36),
+
+                color: Color::RGBA(150, 150, 150, 255),
+
+                text: format!("volume {}%", prefs.volume),
+            },
+
+            mute_button: Button {
+                body: Rect::new(100, 0, 80, 36),
+
+                color: Color::RGBA(150, 150, 150, 255),
+
+                text: String::from(if prefs.is_muted { "unmute" } else { "mute" }),
             },
+
+            music_button: Button {
+                body: Rect::new(190, 0, 80, 36),
+
+                color: Color::RGBA(150, 150, 150, 255),
+
+                text: String::from(if prefs.has_music {
+                    "music on"
+                } else {
+                    "music off"
+                }),
+            },
+
+            prefs: prefs,
+
+            error: None,
+        };
+        state.place_widgets();
+
+        state
+    }
+
+    // in rows around the window's middle, the sound buttons in the
+    // bottom left corner.
+    fn place_widgets(&mut self) {
+        let (x, y) = (self.layout.width / 2, self.layout.height);
+        let place = |body: &mut Rect, left: i32, top: i32| {
+            body.set_x(left);
+            body.set_y(top);
+        };
+
+        place(&mut self.host_field.body, x - 200, y / 3 - 20);
+        place(&mut self.create_button.body, x - 10, y / 3 - 20);
+        place(&mut self.settings_button.body, x + 140, y / 3 - 20);
+
+        place(&mut self.join_field.body, x - 200, y / 2 - 20);
+        place(&mut self.rooms_button.body, x - 10, y / 2 - 20);
+        place(&mut self.queue_button.body, x + 48, y / 2 - 20);
+
+        // easiest on the left.
+        for (i, (button, _)) in self.computer_buttons.iter_mut().enumerate() {
+            place(&mut button.body, x - 100 + i as i32 * 70, y * 2 / 3 - 20);
         }
+
+        place(&mut self.volume_button.body, 10, y - 50);
+        place(&mut self.mute_button.body, 100, y - 50);
+        place(&mut self.music_button.body, 190, y - 50);
     }
 
-    async fn start_server(&mut self) -> (SocketAddr, JoinHandle<mini_redis::Result<()>>) {
-        let listener = TcpListener::bind(self.create_button.text.clone())
-            .await
-            .unwrap();
-        let addr = listener.local_addr().unwrap();
+    // applies and remembers a changed sound choice.
+    fn change_sound(&mut self, audio: &audio::Audio, change: impl FnOnce(&mut prefs::Prefs)) {
+        change(&mut self.prefs);
+        audio.apply(&self.prefs);
+
+        self.volume_button.text = format!("volume {}%", self.prefs.volume);
+        self.mute_button.text = String::from(if self.prefs.is_muted {
+            "unmute"
+        } else {
+            "mute"
+        });
+        self.music_button.text = String::from(if self.prefs.has_music {
+            "music on"
+        } else {
+            "music off"
+        });
 
-        let handle =
-            tokio::spawn(async move { server::run(listener, tokio::signal::ctrl_c()).await });
+        // the addresses may have changed since we loaded them.
+        let path = std::path::Path::new(config::PREFS_FILE);
+        let mut prefs = prefs::Prefs::load(path);
+        prefs.volume = self.prefs.volume;
+        prefs.is_muted = self.prefs.is_muted;
+        prefs.has_music = self.prefs.has_music;
+        if let Err(err) = prefs.save(path) {
+            println!(
+                "<ChooseState> couldn't save {}: {}",
+                config::PREFS_FILE,
+                err
+            );
+        }
+    }
+
+    fn settings(&self) -> settings::MatchSettings {
+        self.presets[self.settings_index].clone()
+    }
+
+    // the chosen board as a grid, with its fleet listed below it.
+    fn draw_settings(&self, canvas: &mut Canvas<Window>, text: &text::Text) {
+        let settings = &self.presets[self.settings_index];
+        let body = self.settings_button.body;
+
+        draw_caption(
+            canvas,
+            text,
+            body,
+            &format!(
+                "{}x{} board, click to change",
+                settings.width, settings.height
+            ),
+        );
+
+        canvas.set_draw_color(self.settings_button.color);
+        canvas.fill_rect(Some(body)).unwrap();
+
+        let preview = geometry::BoardGeometry::fit(body, settings.width, settings.height);
+
+        canvas.set_draw_color(Color::RGBA(255, 255, 255, 255));
+        for (p1, p2) in preview.lines().iter() {
+            canvas.draw_line(*p1, *p2).unwrap();
+        }
+
+        let fleet = settings.ships();
+        fleet_panel::draw(
+            canvas,
+            body.x(),
+            body.y() + PREVIEW_LENGTH + 10,
+            &fleet,
+            &vec![false; fleet.len()],
+            Color::RGBA(0, 255, 0, 200),
+        );
+    }
+
+    // typos are pointed out here, network failures get their own screen.
+    fn check_address(&mut self, address: &str) -> bool {
+        match prefs::check_address(address) {
+            Ok(()) => true,
+            Err(err) => {
+                let message = format!("'{}': {}", address, err);
+                println!("<ChooseState> {}", message);
+                self.error = Some(message);
+                false
+            }
+        }
+    }
+
+    async fn host(&mut self) -> Option<Box<dyn state::State>> {
+        let address = self.host_field.value.clone();
+        if !self.check_address(&address) {
+            return None;
+        }
 
-        (addr, handle)
+        Some(Connect::Host(address, self.settings()).run_or_fail().await)
     }
+
+    // lists the rooms at the join address.
+    async fn browse(&mut self) -> Option<Box<dyn state::State>> {
+        let address = self.join_field.value.clone();
+        if !self.check_address(&address) {
+            return None;
+        }
+
+        Some(
+            Connect::Browse(address, self.settings())
+                .run_or_fail()
+                .await,
+        )
+    }
+
+    async fn queue(&mut self) -> Option<Box<dyn state::State>> {
+        let address = self.join_field.value.clone();
+        if !self.check_address(&address) {
+            return None;
+        }
+
+        Some(Connect::Queue(address).run_or_fail().await)
+    }
+}
+
+// what the lobby asked for, kept so the error screen can try it again.
+#[derive(Debug, Clone)]
+pub enum Connect {
+    // start our own server and open a room on it.
+    Host(String, settings::MatchSettings),
+    // open a room on someone else's server.
+    Create(String, settings::MatchSettings),
+    // list the open rooms of a server.
+    Browse(String, settings::MatchSettings),
+    // the room with this code, the host picks the settings. a host who
+    // dropped out of the room takes their seat again.
+    Join(String, String, settings::MatchSettings),
+    // follow the match in the room with this code.
+    Watch(String, String),
+    // the match server picks the settings.
+    Queue(String),
+}
+
+impl Connect {
+    pub async fn run(&self) -> Result<Box<dyn state::State>, String> {
+        let state: Box<dyn state::State> = match self {
+            Connect::Host(address, settings) => {
+                let (addr, server_handle) = start_server(address)
+                    .await
+                    .map_err(|err| self.failure(err))?;
+                let addr = local_address(addr);
+
+                let code = registry::free_code(&addr)
+                    .await
+                    .map_err(|err| self.failure(err))?;
+
+                Box::new(
+                    initial_state::InitialState::new(
+                        initial_state::Opponent::Host(addr, code, Some(server_handle)),
+                        settings.clone(),
+                    )
+                    .await
+                    .map_err(|err| self.failure(err))?,
+                )
+            }
+
+            Connect::Create(address, settings) => {
+                let code = registry::free_code(address)
+                    .await
+                    .map_err(|err| self.failure(err))?;
+
+                Box::new(
+                    initial_state::InitialState::new(
+                        initial_state::Opponent::Host(address.clone(), code, None),
+                        settings.clone(),
+                    )
+                    .await
+                    .map_err(|err| self.failure(err))?,
+                )
+            }
+
+            Connect::Browse(address, settings) => Box::new(
+                lobby_state::LobbyState::new(address.clone(), settings.clone())
+                    .await
+                    .map_err(|err| self.failure(err))?,
+            ),
+
+            Connect::Join(address, code, settings) => Box::new(
+                initial_state::InitialState::new(
+                    initial_state::Opponent::join(address.clone(), code.clone()),
+                    settings.clone(),
+                )
+                .await
+                .map_err(|err| self.failure(err))?,
+            ),
+
+            Connect::Watch(address, code) => {
+                let room = registry::find(address, code)
+                    .await
+                    .map_err(|err| self.failure(err))?;
+
+                Box::new(
+                    spectate_state::SpectateState::new(address.clone(), room)
+                        .await
+                        .map_err(|err| self.failure(err))?,
+                )
+            }
+
+            Connect::Queue(address) => Box::new(
+                initial_state::InitialState::new(
+                    initial_state::Opponent::Queue(address.clone()),
+                    settings::MatchSettings::default(),
+                )
+                .await
+                .map_err(|err| self.failure(err))?,
+            ),
+        };
+
+        // remember what worked for next time.
+        let path = std::path::Path::new(config::PREFS_FILE);
+        let mut prefs = prefs::Prefs::load(path);
+        match self {
+            Connect::Host(address, _) => prefs.host_address = address.clone(),
+            Connect::Create(address, _)
+            | Connect::Browse(address, _)
+            | Connect::Join(address, ..)
+            | Connect::Watch(address, _)
+            | Connect::Queue(address) => prefs.join_address = address.clone(),
+        }
+        if let Err(err) = prefs.save(path) {
+            println!(
+                "<ChooseState> couldn't save {}: {}",
+                config::PREFS_FILE,
+                err
+            );
+        }
+
+        Ok(state)
+    }
+
+    // what went wrong, for the error screen.
+    fn failure(&self, err: impl std::fmt::Display) -> String {
+        match self {
+            Connect::Host(address, _) => format!("can't host at {}: {}", address, err),
+            Connect::Create(address, _) => format!("can't open a room at {}: {}", address, err),
+            Connect::Browse(address, _) => {
+                format!("can't list the rooms at {}: {}", address, err)
+            }
+            Connect::Join(address, code, _) => {
+                format!("can't join room {} at {}: {}", code, address, err)
+            }
+            Connect::Watch(address, code) => {
+                format!("can't watch room {} at {}: {}", code, address, err)
+            }
+            Connect::Queue(address) => {
+                format!("can't queue on the match server at {}: {}", address, err)
+            }
+        }
+    }
+
+    // the new match, or the error screen offering to try again.
+    pub async fn run_or_fail(self) -> Box<dyn state::State> {
+        match self.run().await {
+            Ok(state) => state,
+            Err(message) => Box::new(error_state::ErrorState::new(message, Some(self))),
+        }
+    }
+}
+
+async fn start_server(
+    address: &str,
+) -> std::io::Result<(SocketAddr, JoinHandle<mini_redis::Result<()>>)> {
+    let listener = TcpListener::bind(address).await?;
+    let addr = listener.local_addr()?;
+
+    let handle = tokio::spawn(async move { server::run(listener, tokio::signal::ctrl_c()).await });
+
+    Ok((addr, handle))
 }
 
 #[async_trait(?Send)]
@@ -98,8 +543,14 @@ impl state::State for ChooseState {
         &mut self,
         event_pump: &mut EventPump,
         next_state: &mut Option<state::NextState>,
+        audio: &audio::Audio,
     ) {
         for event in event_pump.poll_iter() {
+            // typing into an address goes before any shortcut.
+            if self.host_field.handle_event(&event) || self.join_field.handle_event(&event) {
+                continue;
+            }
+
             match event {
                 Event::Quit { .. } => {
                     next_state.replace(state::NextState::Quit);
@@ -112,6 +563,45 @@ impl state::State for ChooseState {
                         return;
                     }
 
+                    // RETURN in an address field hosts or joins right away.
+                    Some(Keycode::Return) => {
+                        let new_state = if self.host_field.is_focused {
+                            self.host().await
+                        } else if self.join_field.is_focused {
+                            self.browse().await
+                        } else {
+                            None
+                        };
+
+                        if let Some(new_state) = new_state {
+                            next_state.replace(state::NextState::Update(new_state));
+                            return;
+                        }
+                    }
+
+                    // watch the last match saved on this machine.
+                    Some(Keycode::R) => {
+                        let path = match replay::latest(std::path::Path::new(config::REPLAY_DIR)) {
+                            Some(path) => path,
+                            None => {
+                                println!("<ChooseState> no replays saved yet");
+                                continue;
+                            }
+                        };
+
+                        match replay::Replay::load(&path) {
+                            Ok(replay) => {
+                                next_state.replace(state::NextState::Update(Box::new(
+                                    replay_state::ReplayState::new(replay),
+                                )));
+                                return;
+                            }
+                            Err(err) => {
+                                println!("<ChooseState> couldn't load {}: {}", path.display(), err)
+                            }
+                        }
+                    }
+
                     _ => {
                         println!("<ChooseState> unused key: {}", keycode.unwrap());
                     }
@@ -123,22 +613,61 @@ impl state::State for ChooseState {
                     y,
                     ..
                 } => {
-                    if self.create_button.is_click(x, y) {
-                        let (_, server_handle) = self.start_server().await;
+                    self.host_field.is_focused = self.host_field.is_click(x, y);
+                    self.join_field.is_focused = self.join_field.is_click(x, y);
+
+                    let mut new_state = None;
+
+                    if self.settings_button.is_click(x, y) {
+                        self.settings_index = (self.settings_index + 1) % self.presets.len();
+                    } else if self.create_button.is_click(x, y) {
+                        new_state = self.host().await;
+                    } else if self.rooms_button.is_click(x, y) {
+                        new_state = self.browse().await;
+                    } else if self.queue_button.is_click(x, y) {
+                        new_state = self.queue().await;
+                    } else if self.volume_button.is_click(x, y) {
+                        // louder in steps, from the loudest back to silent.
+                        self.change_sound(audio, |prefs| {
+                            let louder =
+                                (prefs.volume / config::VOLUME_STEP + 1) * config::VOLUME_STEP;
+                            prefs.volume = if louder > config::MAX_VOLUME {
+                                0
+                            } else {
+                                louder
+                            };
+                        });
+                    } else if self.mute_button.is_click(x, y) {
+                        self.change_sound(audio, |prefs| prefs.is_muted = !prefs.is_muted);
+                    } else if self.music_button.is_click(x, y) {
+                        self.change_sound(audio, |prefs| prefs.has_music = !prefs.has_music);
+                    }
+
+                    let difficulty = self
+                        .computer_buttons
+                        .iter()
+                        .find(|(button, _)| button.is_click(x, y))
+                        .map(|(_, difficulty)| *difficulty);
 
-                        next_state.replace(state::NextState::Update(Box::new(
-                            initial_state::InitialState::new(
-                                self.create_button.text.clone(),
-                                Some(server_handle),
+                    if let Some(difficulty) = difficulty {
+                        new_state = Some(
+                            match initial_state::InitialState::new(
+                                initial_state::Opponent::Computer(difficulty),
+                                self.settings(),
                             )
-                            .await,
-                        )));
-                        return;
-                    } else if self.join_button.is_click(x, y) {
-                        next_state.replace(state::NextState::Update(Box::new(
-                            initial_state::InitialState::new(self.join_button.text.clone(), None)
-                                .await,
-                        )));
+                            .await
+                            {
+                                Ok(state) => Box::new(state),
+                                Err(err) => Box::new(error_state::ErrorState::new(
+                                    format!("can't start the computer: {}", err),
+                                    None,
+                                )),
+                            },
+                        );
+                    }
+
+                    if let Some(new_state) = new_state {
+                        next_state.replace(state::NextState::Update(new_state));
                         return;
                     }
                 }
@@ -150,8 +679,67 @@ impl state::State for ChooseState {
         next_state.replace(state::NextState::Continue);
     }
 
-    async fn draw(&self, canvas: &mut Canvas<Window>) {
-        self.create_button.draw(canvas);
-        self.join_button.draw(canvas);
+    async fn draw(
+        &mut self,
+        canvas: &mut Canvas<Window>,
+        text: &text::Text,
+        _theme: &theme::Theme,
+        _dt: Duration,
+    ) {
+        let white = Color::RGBA(255, 255, 255, 255);
+
+        text.draw_centered(
+            canvas,
+            text::Size::Large,
+            config::WINDOW_TITLE,
+            Point::new(self.layout.width / 2, 60),
+            white,
+        );
+
+        self.host_field.draw(canvas, text);
+        draw_caption(canvas, text, self.host_field.body, "host a game at");
+        self.create_button.draw(canvas, text);
+
+        self.join_field.draw(canvas, text);
+        draw_caption(canvas, text, self.join_field.body, "find a room at");
+        self.rooms_button.draw(canvas, text);
+        self.queue_button.draw(canvas, text);
+
+        for (button, _) in self.computer_buttons.iter() {
+            button.draw(canvas, text);
+        }
+        if let Some((button, _)) = self.computer_buttons.first() {
+            draw_caption(canvas, text, button.body, "play the computer");
+        }
+
+        self.volume_button.draw(canvas, text);
+        draw_caption(canvas, text, self.volume_button.body, "sound");
+        self.mute_button.draw(canvas, text);
+        self.music_button.draw(canvas, text);
+
+        if let Some(error) = self.error.as_ref() {
+            text.draw_centered(
+                canvas,
+                text::Size::Small,
+                error,
+                Point::new(self.layout.width / 2, self.layout.height * 5 / 6),
+                Color::RGBA(255, 80, 80, 255),
+            );
+        }
+
+        self.draw_settings(canvas, text);
+
+        text.draw_centered(
+            canvas,
+            text::Size::Small,
+            "R to watch the last replay, ESC to quit",
+            self.layout.bottom_center(15),
+            white,
+        );
+    }
+
+    fn relayout(&mut self, layout: &layout::Layout) {
+        self.layout = *layout;
+        self.place_widgets();
     }
 }
@@ -0,0 +1,44 @@
+use std::time::{Duration, Instant};
+
+use super::config;
+
+// the frame timing of the game loop. every frame asks how long the last one
+// took, so animations run at the same speed however fast the loop goes.
+pub struct Clock {
+    last_frame: Instant,
+    // the shortest a frame may take, 'None' if the frame rate isn't capped.
+    frame_time: Option<Duration>,
+}
+
+impl Clock {
+    // at most 'frame_cap' frames per second, 0 for no cap.
+    pub fn new(frame_cap: u32) -> Clock {
+        Clock {
+            last_frame: Instant::now(),
+            frame_time: match frame_cap {
+                0 => None,
+                frame_cap => Some(Duration::from_secs(1) / frame_cap),
+            },
+        }
+    }
+
+    // waits out the rest of a capped frame, then gives the time since the
+    // last call. the network tasks run while we wait.
+    //
+    // a long stall, e.g. a dragged window, counts as 'config::MAX_FRAME_TIME'
+    // so animations don't skip to their end.
+    pub async fn tick(&mut self) -> Duration {
+        if let Some(frame_time) = self.frame_time {
+            let elapsed = self.last_frame.elapsed();
+            if elapsed < frame_time {
+                tokio::time::delay_for(frame_time - elapsed).await;
+            }
+        }
+
+        let now = Instant::now();
+        let dt = now.duration_since(self.last_frame);
+        self.last_frame = now;
+
+        std::cmp::min(dt, config::MAX_FRAME_TIME)
+    }
+}
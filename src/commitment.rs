@@ -0,0 +1,353 @@
+// commit-reveal of a fleet, so nobody can answer every shot with a miss.
+// before the first shot each player sends a hash of its fleet and a secret
+// salt, at the end of the match it reveals both and the other player checks
+// every answer it got against the revealed fleet.
+
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use std::fmt;
+
+use super::engine::{Board, Coord, ShipType, ShotResult};
+use super::protocol::{self, ProtocolError};
+use super::replay::{Event, Replay};
+use super::settings::MatchSettings;
+
+// random bytes in a salt, enough that a fleet can't be guessed from its hash.
+const SALT_LENGTH: usize = 16;
+
+pub type Fleet = Vec<(ShipType, Vec<Coord>)>;
+
+// our side of the commitment, the salt stays secret until the reveal.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Commitment {
+    pub salt: String,
+    pub digest: String,
+}
+
+impl Commitment {
+    pub fn new(fleet: &[(ShipType, Vec<Coord>)], rng: &mut impl Rng) -> Commitment {
+        let salt: String = (0..SALT_LENGTH)
+            .map(|_| format!("{:02x}", rng.gen::<u8>()))
+            .collect();
+        let digest = digest(&salt, fleet);
+
+        Commitment { salt, digest }
+    }
+}
+
+// why the opponent forfeits the match.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Cheat {
+    // they shot or answered before committing to a fleet.
+    NoCommitment,
+    // they won but never showed the fleet they won with.
+    NoReveal,
+    // the revealed fleet isn't the one they committed to.
+    WrongDigest,
+    // the revealed fleet can't be placed or isn't the fleet of the match.
+    BadFleet(String),
+    // they answered a shot with something their fleet doesn't say.
+    WrongAnswer {
+        shot: Coord,
+        said: ShotResult,
+        was: ShotResult,
+    },
+}
+
+impl fmt::Display for Cheat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Cheat::NoCommitment => write!(f, "they never committed to a fleet"),
+            Cheat::NoReveal => write!(f, "they never revealed their fleet"),
+            Cheat::WrongDigest => write!(f, "their fleet isn't the one they committed to"),
+            Cheat::BadFleet(reason) => write!(f, "their fleet is invalid: {}", reason),
+            Cheat::WrongAnswer { shot, said, was } => write!(
+                f,
+                "they answered {} at {} {}, it was {}",
+                protocol::encode_result(*said),
+                shot.x,
+                shot.y,
+                protocol::encode_result(*was)
+            ),
+        }
+    }
+}
+
+impl Error for Cheat {}
+
+// how the opponent's answers held up once the match was over.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Verdict {
+    Verified,
+    // we won without seeing their fleet, e.g. they dropped out.
+    Unverified,
+    Forfeit(Cheat),
+    // they left the match on purpose, which forfeits it too.
+    Resigned,
+}
+
+pub fn fleet_of(board: &Board) -> Fleet {
+    board
+        .ships()
+        .iter()
+        .map(|ship| (ship.ship_type(), ship.cells().to_vec()))
+        .collect()
+}
+
+// hex encoded sha-256 of the salt and the fleet.
+pub fn digest(salt: &str, fleet: &[(ShipType, Vec<Coord>)]) -> String {
+    let hash = Sha256::digest(format!("{} {}", salt, encode_fleet(fleet)).as_bytes());
+
+    hash.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// checks every answer the opponent gave in 'replay' against the fleet they
+// revealed with 'salt'.
+pub fn verify(replay: &Replay, salt: &str, fleet: &[(ShipType, Vec<Coord>)]) -> Result<(), Cheat> {
+    let answers: Vec<(Coord, ShotResult)> = replay
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            Event::Shot(shot, said) => Some((*shot, *said)),
+            _ => None,
+        })
+        .collect();
+
+    verify_answers(
+        replay.their_commitment.as_deref(),
+        &replay.settings,
+        salt,
+        fleet,
+        &answers,
+    )
+}
+
+// checks the 'answers' a player gave, in order, against the fleet they
+// revealed and the digest they 'committed' to.
+pub fn verify_answers(
+    committed: Option<&str>,
+    settings: &MatchSettings,
+    salt: &str,
+    fleet: &[(ShipType, Vec<Coord>)],
+    answers: &[(Coord, ShotResult)],
+) -> Result<(), Cheat> {
+    let committed = committed.ok_or(Cheat::NoCommitment)?;
+    if digest(salt, fleet) != committed {
+        return Err(Cheat::WrongDigest);
+    }
+
+    let mut board = Board::new(settings.width, settings.height);
+    for (ship_type, cells) in fleet.iter() {
+        board
+            .place(*ship_type, cells)
+            .map_err(|err| Cheat::BadFleet(format!("{:?}", err)))?;
+    }
+
+    let mut placed: Vec<ShipType> = fleet.iter().map(|(ship_type, _)| *ship_type).collect();
+    let mut expected = settings.ships();
+    placed.sort_by_key(|ship_type| ship_type.name());
+    expected.sort_by_key(|ship_type| ship_type.name());
+    if placed != expected {
+        return Err(Cheat::BadFleet("not the fleet of the match".to_string()));
+    }
+
+    for (shot, said) in answers.iter() {
+        // a repeated shot gets a plain answer, see 'BattleState'.
+        let was = match board.receive_shot(*shot) {
+            Ok(result) => result,
+            Err(_) => match board.ship_at(*shot) {
+                Some(_) => ShotResult::Hit,
+                None => ShotResult::Miss,
+            },
+        };
+
+        if *said != was {
+            return Err(Cheat::WrongAnswer {
+                shot: *shot,
+                said: *said,
+                was,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+// "<ship> <x> <y> <x> <y>...;<ship> ...", the order of placement is kept.
+pub(crate) fn encode_fleet(fleet: &[(ShipType, Vec<Coord>)]) -> String {
+    let ships: Vec<String> = fleet
+        .iter()
+        .map(|(ship_type, cells)| {
+            let cells: Vec<String> = cells.iter().map(|c| format!("{} {}", c.x, c.y)).collect();
+            format!("{} {}", ship_type.name(), cells.join(" "))
+        })
+        .collect();
+
+    ships.join(";")
+}
+
+pub(crate) fn decode_fleet(text: &str) -> Result<Fleet, ProtocolError> {
+    let mut fleet: Fleet = Vec::new();
+
+    for ship in text.split(';').filter(|ship| !ship.trim().is_empty()) {
+        let mut words = ship.split_whitespace();
+
+        let name = words.next().ok_or(ProtocolError::MissingField("ship"))?;
+        let ship_type = ShipType::from_name(name)
+            .ok_or_else(|| ProtocolError::BadField("ship", name.to_string()))?;
+
+        let mut cells: Vec<Coord> = Vec::new();
+        while let Some(x) = words.next() {
+            cells.push(Coord::new(
+                protocol::parse_field(Some(x), "x")?,
+                protocol::parse_field(words.next(), "y")?,
+            ));
+        }
+
+        fleet.push((ship_type, cells));
+    }
+
+    Ok(fleet)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> MatchSettings {
+        MatchSettings {
+            width: 10,
+            height: 10,
+            fleet: vec![(ShipType::Destroyer, 1), (ShipType::PatrolBoat, 1)],
+        }
+    }
+
+    fn fleet() -> Fleet {
+        vec![
+            (
+                ShipType::Destroyer,
+                vec![Coord::new(0, 0), Coord::new(1, 0), Coord::new(2, 0)],
+            ),
+            (
+                ShipType::PatrolBoat,
+                vec![Coord::new(5, 5), Coord::new(5, 6)],
+            ),
+        ]
+    }
+
+    fn check(
+        fleet: &[(ShipType, Vec<Coord>)],
+        answers: &[(Coord, ShotResult)],
+    ) -> Result<(), Cheat> {
+        let commitment = Commitment::new(fleet, &mut rand::thread_rng());
+
+        verify_answers(
+            Some(&commitment.digest),
+            &settings(),
+            &commitment.salt,
+            fleet,
+            answers,
+        )
+    }
+
+    #[test]
+    fn honest_answers() {
+        let answers = vec![
+            (Coord::new(9, 9), ShotResult::Miss),
+            (Coord::new(5, 5), ShotResult::Hit),
+            (Coord::new(5, 6), ShotResult::Sunk(ShipType::PatrolBoat)),
+            // a repeated shot is answered plainly.
+            (Coord::new(5, 5), ShotResult::Hit),
+            (Coord::new(9, 9), ShotResult::Miss),
+        ];
+
+        assert_eq!(check(&fleet(), &answers), Ok(()));
+    }
+
+    #[test]
+    fn no_commitment() {
+        let commitment = Commitment::new(&fleet(), &mut rand::thread_rng());
+
+        assert_eq!(
+            verify_answers(None, &settings(), &commitment.salt, &fleet(), &[]),
+            Err(Cheat::NoCommitment)
+        );
+    }
+
+    #[test]
+    fn wrong_digest() {
+        let commitment = Commitment::new(&fleet(), &mut rand::thread_rng());
+        let mut moved = fleet();
+        moved[1].1 = vec![Coord::new(7, 7), Coord::new(8, 7)];
+
+        assert_eq!(
+            verify_answers(
+                Some(&commitment.digest),
+                &settings(),
+                &commitment.salt,
+                &moved,
+                &[]
+            ),
+            Err(Cheat::WrongDigest)
+        );
+        assert_eq!(
+            verify_answers(Some(&commitment.digest), &settings(), "00", &fleet(), &[]),
+            Err(Cheat::WrongDigest)
+        );
+    }
+
+    #[test]
+    fn bad_fleet() {
+        let mut overlapping = fleet();
+        overlapping[1].1 = vec![Coord::new(2, 0), Coord::new(3, 0)];
+        assert!(matches!(check(&overlapping, &[]), Err(Cheat::BadFleet(_))));
+
+        let mut short = fleet();
+        short.pop();
+        assert!(matches!(check(&short, &[]), Err(Cheat::BadFleet(_))));
+
+        let mut other = fleet();
+        other[1] = (
+            ShipType::Submarine,
+            vec![Coord::new(5, 5), Coord::new(5, 6), Coord::new(5, 7)],
+        );
+        assert!(matches!(check(&other, &[]), Err(Cheat::BadFleet(_))));
+    }
+
+    #[test]
+    fn wrong_answer() {
+        let answers = vec![
+            (Coord::new(9, 9), ShotResult::Miss),
+            (Coord::new(5, 5), ShotResult::Miss),
+        ];
+
+        assert_eq!(
+            check(&fleet(), &answers),
+            Err(Cheat::WrongAnswer {
+                shot: Coord::new(5, 5),
+                said: ShotResult::Miss,
+                was: ShotResult::Hit,
+            })
+        );
+
+        // a sunk ship must be called sunk.
+        let answers = vec![
+            (Coord::new(5, 5), ShotResult::Hit),
+            (Coord::new(5, 6), ShotResult::Hit),
+        ];
+        assert_eq!(
+            check(&fleet(), &answers),
+            Err(Cheat::WrongAnswer {
+                shot: Coord::new(5, 6),
+                said: ShotResult::Hit,
+                was: ShotResult::Sunk(ShipType::PatrolBoat),
+            })
+        );
+    }
+
+    #[test]
+    fn fleet_round_trips() {
+        assert_eq!(decode_fleet(&encode_fleet(&fleet())), Ok(fleet()));
+    }
+}
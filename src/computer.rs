@@ -0,0 +1,151 @@
+use async_trait::async_trait;
+use bytes::Bytes;
+use mini_redis::Result;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::collections::VecDeque;
+
+use super::ai;
+use super::commitment;
+use super::engine;
+use super::peer;
+use super::protocol;
+use super::settings;
+use super::wake;
+
+// plays the other side of the protocol locally, so 'BattleState'
+// runs the same turn loop it uses against a remote player.
+pub struct ComputerOpponent {
+    computer: ai::Computer,
+    // what a rematch sets up a new fleet and strategy with.
+    difficulty: ai::Difficulty,
+    settings: settings::MatchSettings,
+    // the player shoots first, then every rematch swaps.
+    is_first: bool,
+    outbox: VecDeque<protocol::Message>,
+    last_shot: Option<engine::Coord>,
+    is_greeted: bool,
+    // plays fair, but still commits so the player's checks run.
+    commitment: Option<commitment::Commitment>,
+}
+
+impl ComputerOpponent {
+    // 'None' if the fleet doesn't fit the board.
+    pub fn new(
+        difficulty: ai::Difficulty,
+        settings: settings::MatchSettings,
+    ) -> Option<ComputerOpponent> {
+        Some(ComputerOpponent {
+            computer: new_computer(difficulty, &settings)?,
+            difficulty: difficulty,
+            settings: settings,
+            is_first: false,
+            outbox: VecDeque::new(),
+            last_shot: None,
+            is_greeted: false,
+            commitment: None,
+        })
+    }
+
+    // a new fleet and a clean slate, the computer always agrees.
+    fn rematch(&mut self) {
+        match new_computer(self.difficulty, &self.settings) {
+            Some(computer) => self.computer = computer,
+            None => return,
+        }
+
+        self.is_first = !self.is_first;
+        self.last_shot = None;
+        self.is_greeted = false;
+        self.commitment = None;
+        self.outbox.push_back(protocol::Message::Rematch);
+    }
+
+    fn take_turn(&mut self) {
+        self.last_shot = self.computer.next_shot();
+
+        if let Some(shot) = self.last_shot {
+            self.outbox.push_back(protocol::Message::Shot(shot));
+        }
+    }
+}
+
+fn new_computer(
+    difficulty: ai::Difficulty,
+    settings: &settings::MatchSettings,
+) -> Option<ai::Computer> {
+    let mut rng = StdRng::from_entropy();
+    let board = ai::place_fleet(&settings.ships(), settings.width, settings.height, &mut rng)?;
+    let strategy = difficulty.strategy(&settings.ships());
+
+    Some(ai::Computer::new(board, strategy, rng))
+}
+
+#[async_trait]
+impl peer::Peer for ComputerOpponent {
+    async fn send(&mut self, frame: Bytes) -> Result<()> {
+        match protocol::Message::decode(&frame)? {
+            protocol::Message::Hello { .. } if !self.is_greeted => {
+                self.is_greeted = true;
+                self.outbox.push_back(protocol::Message::hello(None));
+            }
+
+            protocol::Message::Shot(shot) => {
+                let result = match self.computer.receive_shot(shot) {
+                    Ok(result) => result,
+                    Err(_) => match self.computer.board().ship_at(shot) {
+                        Some(_) => engine::ShotResult::Hit,
+                        None => engine::ShotResult::Miss,
+                    },
+                };
+                self.outbox.push_back(protocol::Message::ShotResult(result));
+
+                if self.computer.board().is_defeated() {
+                    self.outbox.push_back(protocol::Message::GameOver);
+                } else {
+                    self.take_turn();
+                }
+            }
+
+            protocol::Message::Commit(_) => {
+                let commitment = commitment::Commitment::new(
+                    &commitment::fleet_of(self.computer.board()),
+                    &mut rand::thread_rng(),
+                );
+                self.outbox
+                    .push_back(protocol::Message::Commit(commitment.digest.clone()));
+                self.commitment = Some(commitment);
+
+                if self.is_first {
+                    self.take_turn();
+                }
+            }
+
+            protocol::Message::Reveal(..) => {
+                if let Some(commitment) = self.commitment.as_ref() {
+                    self.outbox.push_back(protocol::Message::Reveal(
+                        commitment.salt.clone(),
+                        commitment::fleet_of(self.computer.board()),
+                    ));
+                }
+            }
+
+            protocol::Message::ShotResult(result) => {
+                if let Some(shot) = self.last_shot.take() {
+                    self.computer.record(shot, result);
+                }
+            }
+
+            protocol::Message::Rematch => self.rematch(),
+
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    async fn receive(&mut self) -> Result<Option<Bytes>> {
+        wake::wake();
+        Ok(self.outbox.pop_front().map(|msg| msg.encode()))
+    }
+}
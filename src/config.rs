@@ -1,4 +1,32 @@
+use std::time::Duration;
+
 pub const WINDOW_TITLE: &str = "Rusty Battleship";
+// the window opens at this size, the player may resize it, see 'layout::Layout'.
 pub const WINDOW_WIDTH: u32 = 800;
 pub const WINDOW_HEIGHT: u32 = 600;
-pub const BOARD_LENGTH: u32 = 10;
+// any smaller and the boards have no room left for their cells.
+pub const MIN_WINDOW_WIDTH: u32 = 480;
+pub const MIN_WINDOW_HEIGHT: u32 = 360;
+pub const REPLAY_DIR: &str = "replays";
+// room around the board for its coordinates and the turn indicator.
+pub const BOARD_MARGIN: u32 = 30;
+pub const PREFS_FILE: &str = "rusty_battleship.prefs";
+// one directory of sprites per theme, see 'theme::Theme'.
+pub const THEME_DIR: &str = "themes";
+// a connected opponent pings this often, and is gone after this long without a frame.
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(1);
+pub const DISCONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+// a match we may drop out of is kept here, and for this long, to resume it.
+pub const RESUME_DIR: &str = "matches";
+pub const RESUME_EXPIRY: Duration = Duration::from_secs(60 * 60);
+// the sound volume in percent, the volume button turns it up in steps.
+pub const MAX_VOLUME: i32 = 100;
+pub const VOLUME_STEP: i32 = 25;
+// games in a series of rematches, more than half of them win it.
+pub const SERIES_BEST_OF: u32 = 3;
+// the longest a frame may take as far as animations are concerned.
+pub const MAX_FRAME_TIME: Duration = Duration::from_millis(100);
+// an idle game keeps drawing for a while after an event or a message, and
+// draws anyway once in a while, see 'Game::wait_for_change'.
+pub const IDLE_LINGER: Duration = Duration::from_millis(250);
+pub const IDLE_REFRESH: Duration = Duration::from_secs(1);
@@ -0,0 +1,591 @@
+// battleship rules without any rendering or networking attached.
+// states, bots and tools drive a match through these types.
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+pub struct Coord {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Coord {
+    pub fn new(x: i32, y: i32) -> Coord {
+        Coord { x, y }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+pub enum ShipType {
+    Carrier,
+    Battleship,
+    Destroyer,
+    Submarine,
+    PatrolBoat,
+}
+
+impl ShipType {
+    pub fn size(self) -> usize {
+        match self {
+            ShipType::Carrier => 5,
+            ShipType::Battleship => 4,
+            ShipType::Destroyer | ShipType::Submarine => 3,
+            ShipType::PatrolBoat => 2,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ShipType::Carrier => "Carrier",
+            ShipType::Battleship => "Battleship",
+            ShipType::Destroyer => "Destroyer",
+            ShipType::Submarine => "Submarine",
+            ShipType::PatrolBoat => "PatrolBoat",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<ShipType> {
+        STANDARD_FLEET
+            .iter()
+            .copied()
+            .find(|ship_type| ship_type.name() == name)
+    }
+}
+
+// the widest gap between ships a player may ask for when placing.
+pub const MAX_SPACING: i32 = 2;
+
+// the classic fleet, in placement order.
+pub const STANDARD_FLEET: [ShipType; 5] = [
+    ShipType::Carrier,
+    ShipType::Battleship,
+    ShipType::Destroyer,
+    ShipType::Submarine,
+    ShipType::PatrolBoat,
+];
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum ShotResult {
+    Miss,
+    Hit,
+    Sunk(ShipType),
+}
+
+impl ShotResult {
+    pub fn is_hit(self) -> bool {
+        self != ShotResult::Miss
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum PlacementError {
+    WrongLength,
+    NotInLine,
+    OutOfBounds,
+    Overlap,
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum ShotError {
+    OutOfBounds,
+    Repeated,
+    NotYourTurn,
+    GameOver,
+}
+
+#[derive(Debug, Clone)]
+pub struct PlacedShip {
+    ship_type: ShipType,
+    cells: Vec<Coord>,
+    hits: Vec<Coord>,
+}
+
+impl PlacedShip {
+    pub fn ship_type(&self) -> ShipType {
+        self.ship_type
+    }
+
+    pub fn cells(&self) -> &[Coord] {
+        &self.cells
+    }
+
+    pub fn is_sunk(&self) -> bool {
+        self.hits.len() == self.cells.len()
+    }
+}
+
+// one player's waters: the fleet and every shot fired at it.
+#[derive(Debug, Clone)]
+pub struct Board {
+    width: i32,
+    height: i32,
+    ships: Vec<PlacedShip>,
+    hits: Vec<Coord>,
+    misses: Vec<Coord>,
+}
+
+impl Board {
+    pub fn new(width: i32, height: i32) -> Board {
+        Board {
+            width,
+            height,
+            ships: Vec::new(),
+            hits: Vec::new(),
+            misses: Vec::new(),
+        }
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    pub fn is_inside(&self, c: Coord) -> bool {
+        c.x >= 0 && c.x < self.width && c.y >= 0 && c.y < self.height
+    }
+
+    pub fn check_placement(
+        &self,
+        ship_type: ShipType,
+        cells: &[Coord],
+    ) -> Result<(), PlacementError> {
+        if cells.len() != ship_type.size() {
+            return Err(PlacementError::WrongLength);
+        }
+
+        // cells must form a straight, gapless line.
+        let horizontal = cells.iter().all(|c| c.y == cells[0].y);
+        let vertical = cells.iter().all(|c| c.x == cells[0].x);
+        let mut axis: Vec<i32> = if horizontal {
+            cells.iter().map(|c| c.x).collect()
+        } else if vertical {
+            cells.iter().map(|c| c.y).collect()
+        } else {
+            return Err(PlacementError::NotInLine);
+        };
+        axis.sort_unstable();
+        if axis.windows(2).any(|w| w[1] != w[0] + 1) {
+            return Err(PlacementError::NotInLine);
+        }
+
+        if cells.iter().any(|c| !self.is_inside(*c)) {
+            return Err(PlacementError::OutOfBounds);
+        }
+
+        if cells.iter().any(|c| self.ship_at(*c).is_some()) {
+            return Err(PlacementError::Overlap);
+        }
+
+        Ok(())
+    }
+
+    pub fn place(&mut self, ship_type: ShipType, cells: &[Coord]) -> Result<(), PlacementError> {
+        self.check_placement(ship_type, cells)?;
+
+        self.ships.push(PlacedShip {
+            ship_type,
+            cells: cells.to_vec(),
+            hits: Vec::new(),
+        });
+
+        Ok(())
+    }
+
+    // whether 'cells' keep 'spacing' free cells to every placed ship,
+    // diagonals included. ships may touch with a spacing of 0.
+    pub fn is_spaced(&self, cells: &[Coord], spacing: i32) -> bool {
+        self.ships
+            .iter()
+            .flat_map(|ship| ship.cells.iter())
+            .all(|other| {
+                cells
+                    .iter()
+                    .all(|c| std::cmp::max((c.x - other.x).abs(), (c.y - other.y).abs()) > spacing)
+            })
+    }
+
+    pub fn ships(&self) -> &[PlacedShip] {
+        &self.ships
+    }
+
+    pub fn ship_at(&self, c: Coord) -> Option<&PlacedShip> {
+        self.ships.iter().find(|ship| ship.cells.contains(&c))
+    }
+
+    pub fn hits(&self) -> &[Coord] {
+        &self.hits
+    }
+
+    pub fn misses(&self) -> &[Coord] {
+        &self.misses
+    }
+
+    pub fn receive_shot(&mut self, c: Coord) -> Result<ShotResult, ShotError> {
+        if !self.is_inside(c) {
+            return Err(ShotError::OutOfBounds);
+        }
+
+        if self.hits.contains(&c) || self.misses.contains(&c) {
+            return Err(ShotError::Repeated);
+        }
+
+        match self.ships.iter_mut().find(|ship| ship.cells.contains(&c)) {
+            Some(ship) => {
+                ship.hits.push(c);
+                self.hits.push(c);
+
+                if ship.is_sunk() {
+                    Ok(ShotResult::Sunk(ship.ship_type))
+                } else {
+                    Ok(ShotResult::Hit)
+                }
+            }
+
+            None => {
+                self.misses.push(c);
+                Ok(ShotResult::Miss)
+            }
+        }
+    }
+
+    // an empty board has nothing left to sink, but it hasn't lost either.
+    pub fn is_defeated(&self) -> bool {
+        !self.ships.is_empty() && self.ships.iter().all(|ship| ship.is_sunk())
+    }
+}
+
+// what a player knows about the opponent's waters.
+#[derive(Debug, Clone)]
+pub struct TargetBoard {
+    width: i32,
+    height: i32,
+    hits: Vec<Coord>,
+    misses: Vec<Coord>,
+    sunk: Vec<ShipType>,
+}
+
+impl TargetBoard {
+    pub fn new(width: i32, height: i32) -> TargetBoard {
+        TargetBoard {
+            width,
+            height,
+            hits: Vec::new(),
+            misses: Vec::new(),
+            sunk: Vec::new(),
+        }
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    pub fn is_shot(&self, c: Coord) -> bool {
+        self.hits.contains(&c) || self.misses.contains(&c)
+    }
+
+    pub fn check_shot(&self, c: Coord) -> Result<(), ShotError> {
+        if c.x < 0 || c.x >= self.width || c.y < 0 || c.y >= self.height {
+            return Err(ShotError::OutOfBounds);
+        }
+
+        if self.is_shot(c) {
+            return Err(ShotError::Repeated);
+        }
+
+        Ok(())
+    }
+
+    pub fn record(&mut self, c: Coord, result: ShotResult) {
+        if self.is_shot(c) {
+            return;
+        }
+
+        match result {
+            ShotResult::Miss => self.misses.push(c),
+            ShotResult::Hit => self.hits.push(c),
+            ShotResult::Sunk(ship_type) => {
+                self.hits.push(c);
+                self.sunk.push(ship_type);
+            }
+        }
+    }
+
+    pub fn hits(&self) -> &[Coord] {
+        &self.hits
+    }
+
+    pub fn misses(&self) -> &[Coord] {
+        &self.misses
+    }
+
+    pub fn sunk(&self) -> &[ShipType] {
+        &self.sunk
+    }
+
+    // where the ship sunk by the shot at 'c' lay, as far as the hits tell:
+    // a row or a column of exactly its size through 'c'. ships touching in
+    // line with it leave this open.
+    pub fn sunk_cells(&self, c: Coord, ship_type: ShipType) -> Option<Vec<Coord>> {
+        let mut found: Option<Vec<Coord>> = None;
+
+        for (dx, dy) in [(1, 0), (0, 1)].iter() {
+            let mut start = c;
+            while self.hits.contains(&Coord::new(start.x - dx, start.y - dy)) {
+                start = Coord::new(start.x - dx, start.y - dy);
+            }
+
+            let mut cells: Vec<Coord> = Vec::new();
+            let mut next = start;
+            while self.hits.contains(&next) {
+                cells.push(next);
+                next = Coord::new(next.x + dx, next.y + dy);
+            }
+
+            if cells.len() == ship_type.size() {
+                if found.is_some() {
+                    return None;
+                }
+                found = Some(cells);
+            }
+        }
+
+        found
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Side {
+    First,
+    Second,
+}
+
+impl Side {
+    pub fn other(self) -> Side {
+        match self {
+            Side::First => Side::Second,
+            Side::Second => Side::First,
+        }
+    }
+
+    pub fn index(self) -> usize {
+        match self {
+            Side::First => 0,
+            Side::Second => 1,
+        }
+    }
+}
+
+// a whole match with both fleets known, e.g. for bots and tools.
+// networked games only ever see one 'Board' and one 'TargetBoard'.
+#[derive(Debug, Clone)]
+pub struct Match {
+    boards: [Board; 2],
+    targets: [TargetBoard; 2],
+    turn: Side,
+    winner: Option<Side>,
+}
+
+impl Match {
+    pub fn new(first: Board, second: Board) -> Match {
+        let targets = [
+            TargetBoard::new(second.width(), second.height()),
+            TargetBoard::new(first.width(), first.height()),
+        ];
+
+        Match {
+            boards: [first, second],
+            targets,
+            turn: Side::First,
+            winner: None,
+        }
+    }
+
+    pub fn turn(&self) -> Side {
+        self.turn
+    }
+
+    pub fn winner(&self) -> Option<Side> {
+        self.winner
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.winner.is_some()
+    }
+
+    pub fn board(&self, side: Side) -> &Board {
+        &self.boards[side.index()]
+    }
+
+    pub fn target(&self, side: Side) -> &TargetBoard {
+        &self.targets[side.index()]
+    }
+
+    // 'side' fires at the other side's board.
+    pub fn fire(&mut self, side: Side, c: Coord) -> Result<ShotResult, ShotError> {
+        if self.winner.is_some() {
+            return Err(ShotError::GameOver);
+        }
+
+        if side != self.turn {
+            return Err(ShotError::NotYourTurn);
+        }
+
+        let result = self.boards[side.other().index()].receive_shot(c)?;
+        self.targets[side.index()].record(c, result);
+
+        if self.boards[side.other().index()].is_defeated() {
+            self.winner = Some(side);
+        } else {
+            self.turn = side.other();
+        }
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(x: i32, y: i32, size: i32) -> Vec<Coord> {
+        (0..size).map(|i| Coord::new(x + i, y)).collect()
+    }
+
+    #[test]
+    fn placement_errors() {
+        let mut board = Board::new(10, 10);
+        board.place(ShipType::Destroyer, &line(0, 0, 3)).unwrap();
+
+        assert_eq!(
+            board.check_placement(ShipType::Carrier, &line(0, 5, 4)),
+            Err(PlacementError::WrongLength)
+        );
+        let bent = vec![Coord::new(0, 5), Coord::new(1, 5), Coord::new(1, 6)];
+        assert_eq!(
+            board.check_placement(ShipType::Submarine, &bent),
+            Err(PlacementError::NotInLine)
+        );
+        let gap = vec![Coord::new(0, 5), Coord::new(1, 5), Coord::new(3, 5)];
+        assert_eq!(
+            board.check_placement(ShipType::Submarine, &gap),
+            Err(PlacementError::NotInLine)
+        );
+        assert_eq!(
+            board.check_placement(ShipType::Submarine, &line(8, 5, 3)),
+            Err(PlacementError::OutOfBounds)
+        );
+        assert_eq!(
+            board.check_placement(ShipType::Submarine, &line(2, 0, 3)),
+            Err(PlacementError::Overlap)
+        );
+        assert_eq!(
+            board.check_placement(ShipType::Submarine, &line(3, 0, 3)),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn spacing() {
+        let mut board = Board::new(10, 10);
+        board.place(ShipType::PatrolBoat, &line(0, 0, 2)).unwrap();
+
+        assert!(board.is_spaced(&line(2, 1, 2), 0));
+        assert!(!board.is_spaced(&line(2, 1, 2), 1));
+        assert!(board.is_spaced(&line(3, 0, 2), 1));
+    }
+
+    #[test]
+    fn shots() {
+        let mut board = Board::new(10, 10);
+        board.place(ShipType::PatrolBoat, &line(0, 0, 2)).unwrap();
+
+        assert_eq!(
+            board.receive_shot(Coord::new(10, 0)),
+            Err(ShotError::OutOfBounds)
+        );
+        assert_eq!(board.receive_shot(Coord::new(5, 5)), Ok(ShotResult::Miss));
+        assert_eq!(
+            board.receive_shot(Coord::new(5, 5)),
+            Err(ShotError::Repeated)
+        );
+        assert_eq!(board.receive_shot(Coord::new(0, 0)), Ok(ShotResult::Hit));
+        assert_eq!(
+            board.receive_shot(Coord::new(0, 0)),
+            Err(ShotError::Repeated)
+        );
+        assert!(!board.is_defeated());
+        assert_eq!(
+            board.receive_shot(Coord::new(1, 0)),
+            Ok(ShotResult::Sunk(ShipType::PatrolBoat))
+        );
+        assert!(board.is_defeated());
+
+        assert!(!Board::new(10, 10).is_defeated());
+    }
+
+    #[test]
+    fn target_board() {
+        let mut target = TargetBoard::new(10, 10);
+        target.record(Coord::new(0, 0), ShotResult::Hit);
+        target.record(Coord::new(1, 0), ShotResult::Sunk(ShipType::PatrolBoat));
+        target.record(Coord::new(5, 5), ShotResult::Miss);
+
+        assert_eq!(
+            target.check_shot(Coord::new(-1, 0)),
+            Err(ShotError::OutOfBounds)
+        );
+        assert_eq!(
+            target.check_shot(Coord::new(5, 5)),
+            Err(ShotError::Repeated)
+        );
+        assert_eq!(
+            target.check_shot(Coord::new(0, 0)),
+            Err(ShotError::Repeated)
+        );
+        assert_eq!(target.check_shot(Coord::new(2, 0)), Ok(()));
+        assert_eq!(target.sunk(), &[ShipType::PatrolBoat]);
+    }
+
+    #[test]
+    fn turns() {
+        let mut first = Board::new(10, 10);
+        first.place(ShipType::PatrolBoat, &line(0, 0, 2)).unwrap();
+        let mut second = Board::new(10, 10);
+        second.place(ShipType::PatrolBoat, &line(0, 9, 2)).unwrap();
+        let mut game = Match::new(first, second);
+
+        assert_eq!(
+            game.fire(Side::Second, Coord::new(0, 0)),
+            Err(ShotError::NotYourTurn)
+        );
+        assert_eq!(
+            game.fire(Side::First, Coord::new(0, 9)),
+            Ok(ShotResult::Hit)
+        );
+        assert_eq!(game.turn(), Side::Second);
+        assert_eq!(
+            game.fire(Side::First, Coord::new(1, 9)),
+            Err(ShotError::NotYourTurn)
+        );
+        assert_eq!(
+            game.fire(Side::Second, Coord::new(5, 5)),
+            Ok(ShotResult::Miss)
+        );
+        assert_eq!(
+            game.fire(Side::First, Coord::new(1, 9)),
+            Ok(ShotResult::Sunk(ShipType::PatrolBoat))
+        );
+
+        assert!(game.is_over());
+        assert_eq!(game.winner(), Some(Side::First));
+        assert_eq!(
+            game.fire(Side::Second, Coord::new(0, 0)),
+            Err(ShotError::GameOver)
+        );
+    }
+}
@@ -0,0 +1,198 @@
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+use sdl2::pixels::Color;
+use sdl2::rect::Point;
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+use sdl2::EventPump;
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+use super::audio;
+use super::choose_state;
+use super::layout;
+use super::state;
+use super::text;
+use super::theme;
+
+// any state that loses its connection ends up here instead of panicking.
+pub struct ErrorState {
+    layout: layout::Layout,
+    message: String,
+    // 'None' when there is nothing to try again, e.g. mid match.
+    retry: Option<choose_state::Connect>,
+
+    retry_button: choose_state::Button,
+    lobby_button: choose_state::Button,
+}
+
+impl ErrorState {
+    pub fn new(message: String, retry: Option<choose_state::Connect>) -> ErrorState {
+        println!("<ErrorState> {}", message);
+
+        let mut error_state = ErrorState {
+            layout: layout::Layout::default(),
+            message: message,
+            retry: retry,
+
+            retry_button: choose_state::Button {
+                body: Rect::new(0, 0, 120, 40),
+
+                color: Color::RGBA(0, 0, 255, 255),
+
+                text: String::from("try again"),
+            },
+
+            lobby_button: choose_state::Button {
+                body: Rect::new(0, 0, 120, 40),
+
+                color: Color::RGBA(0, 255, 0, 255),
+
+                text: String::from("back to lobby"),
+            },
+        };
+        error_state.place_buttons();
+
+        error_state
+    }
+
+    // side by side under the message.
+    fn place_buttons(&mut self) {
+        let (x, y) = (self.layout.width / 2, self.layout.height / 2 + 20);
+        self.retry_button.body.set_x(x - 130);
+        self.retry_button.body.set_y(y);
+        self.lobby_button.body.set_x(x + 10);
+        self.lobby_button.body.set_y(y);
+    }
+
+    async fn try_again(&mut self) -> Option<Box<dyn state::State>> {
+        let retry = self.retry.as_ref()?;
+
+        match retry.run().await {
+            Ok(state) => Some(state),
+            Err(message) => {
+                println!("<ErrorState> {}", message);
+                self.message = message;
+                None
+            }
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl state::State for ErrorState {
+    async fn handle_events(
+        &mut self,
+        event_pump: &mut EventPump,
+        next_state: &mut Option<state::NextState>,
+        _audio: &audio::Audio,
+    ) {
+        for event in event_pump.poll_iter() {
+            let mut is_retry = false;
+            let mut is_lobby = false;
+
+            match event {
+                Event::Quit { .. } => {
+                    next_state.replace(state::NextState::Quit);
+                    return;
+                }
+
+                Event::KeyDown { keycode, .. } => match keycode {
+                    Some(Keycode::Escape) => {
+                        next_state.replace(state::NextState::Quit);
+                        return;
+                    }
+
+                    Some(Keycode::Return) => is_retry = true,
+
+                    Some(Keycode::Backspace) => is_lobby = true,
+
+                    _ => {
+                        if let Some(keycode) = keycode {
+                            println!("<ErrorState> unused key: {}", keycode);
+                        }
+                    }
+                },
+
+                Event::MouseButtonDown {
+                    mouse_btn: sdl2::mouse::MouseButton::Left,
+                    x,
+                    y,
+                    ..
+                } => {
+                    is_retry = self.retry_button.is_click(x, y);
+                    is_lobby = self.lobby_button.is_click(x, y);
+                }
+
+                _ => {}
+            }
+
+            if is_retry {
+                if let Some(new_state) = self.try_again().await {
+                    next_state.replace(state::NextState::Update(new_state));
+                    return;
+                }
+            }
+
+            if is_lobby {
+                next_state.replace(state::NextState::Update(Box::new(
+                    choose_state::ChooseState::new(),
+                )));
+                return;
+            }
+        }
+
+        next_state.replace(state::NextState::Continue);
+    }
+
+    async fn draw(
+        &mut self,
+        canvas: &mut Canvas<Window>,
+        text: &text::Text,
+        _theme: &theme::Theme,
+        _dt: Duration,
+    ) {
+        let center_x = self.layout.width / 2;
+
+        text.draw_centered(
+            canvas,
+            text::Size::Large,
+            "something went wrong",
+            Point::new(center_x, self.layout.height / 3),
+            Color::RGBA(255, 80, 80, 255),
+        );
+        text.draw_centered(
+            canvas,
+            text::Size::Normal,
+            &self.message,
+            Point::new(center_x, self.layout.height / 2 - 20),
+            Color::RGBA(255, 255, 255, 255),
+        );
+
+        if self.retry.is_some() {
+            self.retry_button.draw(canvas, text);
+        }
+        self.lobby_button.draw(canvas, text);
+
+        let hint = if self.retry.is_some() {
+            "RETURN to try again, BACKSPACE for the lobby, ESC to quit"
+        } else {
+            "BACKSPACE for the lobby, ESC to quit"
+        };
+        text.draw_centered(
+            canvas,
+            text::Size::Small,
+            hint,
+            self.layout.bottom_center(15),
+            Color::RGBA(255, 255, 255, 255),
+        );
+    }
+
+    fn relayout(&mut self, layout: &layout::Layout) {
+        self.layout = *layout;
+        self.place_buttons();
+    }
+}
@@ -0,0 +1,67 @@
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+
+use super::engine;
+
+const CELL: i32 = 12;
+const GAP: i32 = 2;
+const ROW: i32 = 18;
+
+// which ships of 'fleet' are sunk, taking each sunk type only once.
+pub fn sunk_flags(fleet: &[engine::ShipType], sunk: &[engine::ShipType]) -> Vec<bool> {
+    let mut sunk = sunk.to_vec();
+
+    fleet
+        .iter()
+        .map(|ship_type| match sunk.iter().position(|t| t == ship_type) {
+            Some(i) => {
+                sunk.remove(i);
+                true
+            }
+            None => false,
+        })
+        .collect()
+}
+
+// how wide 'draw' makes the panel, the longest ship's row.
+pub fn width(fleet: &[engine::ShipType]) -> i32 {
+    let size = fleet.iter().map(|t| t.size() as i32).max().unwrap_or(0);
+
+    std::cmp::max(size * (CELL + GAP) - GAP, 0)
+}
+
+// one row per ship, sunk ships dimmed and crossed out.
+pub fn draw(
+    canvas: &mut Canvas<Window>,
+    x: i32,
+    y: i32,
+    fleet: &[engine::ShipType],
+    is_sunk: &[bool],
+    color: Color,
+) {
+    for (i, (ship_type, is_sunk)) in fleet.iter().zip(is_sunk.iter()).enumerate() {
+        let row_y = y + i as i32 * ROW;
+        let size = ship_type.size() as i32;
+
+        let cells: Vec<Rect> = (0..size)
+            .map(|j| Rect::new(x + j * (CELL + GAP), row_y, CELL as u32, CELL as u32))
+            .collect();
+
+        if *is_sunk {
+            canvas.set_draw_color(Color::RGBA(color.r, color.g, color.b, 60));
+        } else {
+            canvas.set_draw_color(color);
+        }
+        canvas.fill_rects(&cells[..]).unwrap();
+
+        if *is_sunk {
+            let end_x = x + size * (CELL + GAP) - GAP;
+
+            canvas.set_draw_color(Color::RGBA(255, 0, 0, 255));
+            canvas.draw_line((x, row_y), (end_x, row_y + CELL)).unwrap();
+            canvas.draw_line((x, row_y + CELL), (end_x, row_y)).unwrap();
+        }
+    }
+}
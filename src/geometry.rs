@@ -0,0 +1,147 @@
+use sdl2::rect::Point;
+use sdl2::rect::Rect;
+
+use super::config;
+use super::engine;
+use super::layout;
+
+// where a board sits on screen. states map cells to pixels and pixels back
+// to cells through one of these instead of working out offsets themselves.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct BoardGeometry {
+    // top left corner of the first cell.
+    pub origin: Point,
+    // side of a cell in pixels.
+    pub interval: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl BoardGeometry {
+    // as large as fits into the square 'area', centered in it.
+    pub fn fit(area: Rect, width: i32, height: i32) -> BoardGeometry {
+        let interval = std::cmp::min(area.width(), area.height()) as i32
+            / std::cmp::max(std::cmp::max(width, height), 1);
+
+        BoardGeometry {
+            origin: Point::new(
+                area.x() + (area.width() as i32 - interval * width) / 2,
+                area.y() + (area.height() as i32 - interval * height) / 2,
+            ),
+            interval: interval,
+            width: width,
+            height: height,
+        }
+    }
+
+    // the one board of placement, with room around it for its coordinates
+    // and the status line.
+    pub fn centered(layout: &layout::Layout, width: i32, height: i32) -> BoardGeometry {
+        let min_wh: i32 =
+            std::cmp::min(layout.width, layout.height) - 2 * config::BOARD_MARGIN as i32;
+
+        let interval: i32 = min_wh / std::cmp::max(std::cmp::max(width, height), 1);
+
+        BoardGeometry {
+            origin: Point::new(
+                (layout.width - interval * width) / 2,
+                (layout.height - interval * height) / 2,
+            ),
+            interval: interval,
+            width: width,
+            height: height,
+        }
+    }
+
+    // two boards at the window's sides, left and right.
+    pub fn side_by_side(
+        layout: &layout::Layout,
+        width: i32,
+        height: i32,
+    ) -> (BoardGeometry, BoardGeometry) {
+        let min_wh: i32 = std::cmp::min(layout.width, layout.height);
+
+        let offset: i32 = min_wh / 2;
+        let margin: i32 = offset / 10;
+        let interval: i32 = offset / std::cmp::max(std::cmp::max(width, height), 1);
+
+        let y_offset: i32 = (layout.height - interval * height) / 2;
+        let left = BoardGeometry {
+            origin: Point::new(margin, y_offset),
+            interval: interval,
+            width: width,
+            height: height,
+        };
+        let right = BoardGeometry {
+            origin: Point::new(layout.width - margin - interval * width, y_offset),
+            ..left
+        };
+
+        (left, right)
+    }
+
+    // the whole board in pixels.
+    pub fn body(&self) -> Rect {
+        Rect::new(
+            self.origin.x,
+            self.origin.y,
+            (self.interval * self.width) as u32,
+            (self.interval * self.height) as u32,
+        )
+    }
+
+    pub fn cell_rect(&self, c: engine::Coord) -> Rect {
+        Rect::new(
+            self.origin.x + c.x * self.interval,
+            self.origin.y + c.y * self.interval,
+            self.interval as u32,
+            self.interval as u32,
+        )
+    }
+
+    pub fn cell_rects(&self, cells: &[engine::Coord]) -> Vec<Rect> {
+        cells.iter().map(|c| self.cell_rect(*c)).collect()
+    }
+
+    // the cell under the pixel, if it's on the board.
+    pub fn cell_at(&self, x: i32, y: i32) -> Option<engine::Coord> {
+        // a window squeezed small enough leaves cells no pixels at all.
+        if self.interval <= 0 || self.width <= 0 || self.height <= 0 {
+            return None;
+        }
+        if !self.body().contains_point((x, y)) {
+            return None;
+        }
+
+        Some(engine::Coord::new(
+            (x - self.origin.x) / self.interval,
+            (y - self.origin.y) / self.interval,
+        ))
+    }
+
+    // the grid, every line from one end to the other.
+    pub fn lines(&self) -> Vec<(Point, Point)> {
+        let mut line_points: Vec<(Point, Point)> =
+            Vec::with_capacity((self.width + self.height + 2) as usize);
+
+        let (x, y) = (self.origin.x, self.origin.y);
+
+        // vertical lines.
+        for i in 0..=self.width {
+            line_points.push((
+                Point::new(x + self.interval * i, y),
+                Point::new(x + self.interval * i, y + self.interval * self.height),
+            ));
+        }
+
+        // horizontal lines.
+        for i in 0..=self.height {
+            line_points.push((
+                Point::new(x, y + self.interval * i),
+                Point::new(x + self.interval * self.width, y + self.interval * i),
+            ));
+        }
+
+        line_points
+    }
+}
@@ -1,7 +1,6 @@
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
 use sdl2::pixels::Color;
-use sdl2::rect::Point;
 use sdl2::rect::Rect;
 use sdl2::render::Canvas;
 use sdl2::video::Window;
@@ -10,79 +9,383 @@ use sdl2::EventPump;
 use std::vec::Vec;
 
 use async_trait::async_trait;
-use tokio::task::JoinHandle;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Mutex, Notify};
 
+use super::ai;
+use super::audio;
 use super::battle_state;
+use super::chat;
+use super::chat_panel;
+use super::choose_state;
+use super::computer;
 use super::config;
+use super::engine;
+use super::error_state;
+use super::geometry;
+use super::layout;
+use super::peer;
+use super::prefs;
+use super::protocol;
+use super::registry;
+use super::replay;
+use super::room;
+use super::series;
+use super::session;
+use super::settings;
 use super::ship;
 use super::state;
+use super::text;
+use super::theme;
+
+pub enum Opponent {
+    // we open the room with this code at this address and shoot first,
+    // the server is ours to keep running if we started it.
+    Host(String, String, Option<session::ServerHandle>),
+    // the room with this code at this address.
+    Join(String, String),
+    // the room with this code at this address we hosted and dropped out of,
+    // its guest is still waiting for us.
+    Rejoin(String, String),
+    // a match server at this address pairs us with whoever queues next.
+    Queue(String),
+    Computer(ai::Difficulty),
+}
 
-type ServerHandle = JoinHandle<mini_redis::Result<()>>;
+impl Opponent {
+    // the room with this code at this address, as its host again if that's
+    // the seat we dropped out of.
+    pub fn join(addr: String, code: String) -> Opponent {
+        if chat::has_dropped_match(&addr, &room::channel(&code, engine::Side::Second)) {
+            Opponent::Rejoin(addr, code)
+        } else {
+            Opponent::Join(addr, code)
+        }
+    }
+
+    // how the opponent is named in replays.
+    fn name(&self) -> String {
+        match self {
+            Opponent::Host(..) | Opponent::Rejoin(..) => "guest".to_string(),
+            Opponent::Join(..) => "host".to_string(),
+            Opponent::Queue(..) => "opponent".to_string(),
+            Opponent::Computer(difficulty) => format!("computer ({:?})", difficulty).to_lowercase(),
+        }
+    }
+
+    fn room(&self) -> Option<String> {
+        match self {
+            Opponent::Host(_, code, _) | Opponent::Join(_, code) | Opponent::Rejoin(_, code) => {
+                Some(code.clone())
+            }
+            _ => None,
+        }
+    }
+}
+
+// how we are named in replays.
+fn player_name() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "player".to_string())
+}
+
+// tries per ship, and per fleet, before random placement gives up on a layout.
+const PLACEMENT_ATTEMPTS: usize = 1000;
+const LAYOUT_ATTEMPTS: usize = 100;
+
+enum Handshake {
+    Waiting,
+    // a match server also tells us whether we shoot first.
+    Done(settings::MatchSettings, Option<bool>),
+    // the opponent still had our dropped match and told us how it went on.
+    Resumed(replay::Replay),
+    Failed(String),
+}
 
 pub struct InitialState {
-    board_lines: Vec<(Point, Point)>,
-    ships: Vec<ship::Ship>,
+    layout: layout::Layout,
+    geometry: geometry::BoardGeometry,
+    board: engine::Board,
 
-    ships_t: [ship::ShipType; 5],
+    ships_t: Vec<ship::ShipType>,
     curr_ship_index: usize,
     curr_ship: Option<ship::Ship>,
-
-    addr: String,
-    server_handle: Option<ServerHandle>,
+    // the cell of the current ship held by the mouse, while it's dragged.
+    dragged_part: Option<usize>,
+    // free cells kept between our ships, from the prefs.
+    spacing: i32,
+    // a fleet placed at random waits for a look before we sail.
+    is_confirmed: bool,
+    // played once the events are handled.
+    sounds: Vec<audio::Sound>,
+
+    random_button: choose_state::Button,
+    shuffle_button: choose_state::Button,
+    spacing_button: choose_state::Button,
+
+    // 'None' while a joiner waits for the host's settings.
+    settings: Option<settings::MatchSettings>,
+    handshake: Arc<Mutex<Handshake>>,
+
+    session: session::Session,
 }
 
 impl InitialState {
-    pub async fn new(addr: String, server_handle: Option<ServerHandle>) -> InitialState {
-        let ships_t = [
-            ship::ShipType::Carrier,
-            ship::ShipType::Battleship,
-            ship::ShipType::Destroyer,
-            ship::ShipType::Submarine,
-            ship::ShipType::PatrolBoat,
-        ];
-
-        InitialState {
-            board_lines: InitialState::generate_board_lines(),
-            ships: Vec::new(),
-            ships_t: ships_t,
-            curr_ship_index: 1,
-            curr_ship: Some(ship::Ship::new(ships_t[0])),
-            addr: addr,
+    pub async fn new(
+        opponent: Opponent,
+        settings: settings::MatchSettings,
+    ) -> mini_redis::Result<InitialState> {
+        let opponent_name = opponent.name();
+        let room = opponent.room();
+        let (mut peer, server_handle, is_first, settings, talk) =
+            InitialState::connect(opponent, settings).await?;
+
+        // a match we dropped out of, the fleet we placed in it.
+        let dropped = match peer.load_match().await? {
+            Some(state) => replay::Replay::decode(&String::from_utf8_lossy(&state)).ok(),
+            None => None,
+        };
+
+        let session = session::Session {
+            peer: Arc::new(Mutex::new(peer)),
             server_handle: server_handle,
+            opponent_name: opponent_name.clone(),
+            room: room,
+            is_first: is_first,
+            chat_panel: talk.map(|talk| chat_panel::ChatPanel::new(talk, opponent_name)),
+            series: series::Series::new(config::SERIES_BEST_OF),
+            resigned: Arc::new(Notify::new()),
+        };
+
+        Ok(InitialState::start(session, settings, dropped))
+    }
+
+    // the next game against the same opponent, both asked for it.
+    // whoever shot second shoots first this time.
+    pub fn rematch(
+        mut session: session::Session,
+        settings: settings::MatchSettings,
+    ) -> InitialState {
+        session.is_first = !session.is_first;
+        session.series = session.series.next();
+
+        InitialState::start(session, Some(settings), None)
+    }
+
+    fn start(
+        session: session::Session,
+        settings: Option<settings::MatchSettings>,
+        dropped: Option<replay::Replay>,
+    ) -> InitialState {
+        let layout = layout::Layout::default();
+
+        let mut state = InitialState {
+            layout: layout,
+            geometry: geometry::BoardGeometry::centered(&layout, 0, 0),
+            board: engine::Board::new(0, 0),
+            ships_t: Vec::new(),
+            curr_ship_index: 0,
+            curr_ship: None,
+            dragged_part: None,
+            spacing: 0,
+            is_confirmed: true,
+            sounds: Vec::new(),
+
+            random_button: choose_state::Button {
+                body: Rect::new(10, 80, 110, 36),
+
+                color: Color::RGBA(0, 255, 0, 255),
+
+                text: String::from("random"),
+            },
+
+            shuffle_button: choose_state::Button {
+                body: Rect::new(10, 126, 110, 36),
+
+                color: Color::RGBA(0, 0, 255, 255),
+
+                text: String::from("shuffle"),
+            },
+
+            spacing_button: choose_state::Button {
+                body: Rect::new(10, 200, 110, 36),
+
+                color: Color::RGBA(150, 150, 150, 255),
+
+                text: String::new(),
+            },
+
+            settings: None,
+            handshake: Arc::new(Mutex::new(Handshake::Waiting)),
+            session: session,
+        };
+        state.set_spacing(prefs::Prefs::load(std::path::Path::new(config::PREFS_FILE)).spacing);
+
+        // the host plays by its own settings and can start placing right away.
+        if let Some(settings) = settings.clone() {
+            state.apply_settings(settings);
         }
+        state.handshake(settings, dropped);
+
+        state
     }
 
-    fn generate_board_lines() -> Vec<(Point, Point)> {
-        let mut line_points: Vec<(Point, Point)> =
-            Vec::with_capacity(((config::BOARD_LENGTH + 1) * 2) as usize);
-
-        let x_offset: i32 = (config::WINDOW_WIDTH as i32 - config::WINDOW_HEIGHT as i32) / 2;
-        let min_wh: i32 = std::cmp::min(config::WINDOW_WIDTH as i32, config::WINDOW_HEIGHT as i32);
-
-        let x_interval: i32 = min_wh / 10;
-        let y_interval: i32 = min_wh / 10;
-
-        for i in 0..=config::BOARD_LENGTH {
-            // vertical lines.
-            line_points.push((
-                Point::new(x_interval * i as i32 + x_offset, 0),
-                Point::new(
-                    x_interval * i as i32 + x_offset,
-                    config::WINDOW_HEIGHT as i32,
-                ),
-            ));
-
-            // horizontal lines.
-            line_points.push((
-                Point::new(x_offset, y_interval * i as i32),
-                Point::new(
-                    config::WINDOW_WIDTH as i32 - x_offset,
-                    y_interval * i as i32,
-                ),
-            ));
+    // opens the line to the opponent, returns it with the server handle,
+    // whether we shoot first, the settings we decide on, if any, and the
+    // line to chat on.
+    async fn connect(
+        opponent: Opponent,
+        settings: settings::MatchSettings,
+    ) -> mini_redis::Result<(
+        Box<dyn peer::Peer>,
+        Option<session::ServerHandle>,
+        bool,
+        Option<settings::MatchSettings>,
+        Option<chat::Talk>,
+    )> {
+        match opponent {
+            Opponent::Host(addr, code, server_handle) => {
+                let (receive, send) = (
+                    room::channel(&code, engine::Side::Second),
+                    room::channel(&code, engine::Side::First),
+                );
+                let chat = chat::Chat::new(addr.clone(), receive.clone(), send.clone()).await?;
+                let talk = chat::Talk::new(addr.clone(), &receive, &send).await?;
+
+                // listed once we listen, so the room isn't taken for abandoned.
+                registry::open(
+                    &addr,
+                    room::Room {
+                        code: code,
+                        settings: settings.clone(),
+                        host: player_name(),
+                        is_open: true,
+                    },
+                )
+                .await?;
+
+                Ok((
+                    Box::new(chat),
+                    server_handle,
+                    true,
+                    Some(settings),
+                    Some(talk),
+                ))
+            }
+
+            Opponent::Join(addr, code) => {
+                registry::check_seat(&addr, &code).await?;
+
+                let (receive, send) = (
+                    room::channel(&code, engine::Side::First),
+                    room::channel(&code, engine::Side::Second),
+                );
+                let chat = chat::Chat::new(addr.clone(), receive.clone(), send.clone()).await?;
+                let talk = chat::Talk::new(addr.clone(), &receive, &send).await?;
+
+                registry::start(&addr, &code).await?;
+
+                Ok((Box::new(chat), None, false, None, Some(talk)))
+            }
+
+            Opponent::Rejoin(addr, code) => {
+                registry::check_host_seat(&addr, &code).await?;
+
+                let (receive, send) = (
+                    room::channel(&code, engine::Side::Second),
+                    room::channel(&code, engine::Side::First),
+                );
+                let mut chat = chat::Chat::new(addr.clone(), receive.clone(), send.clone()).await?;
+                let talk = chat::Talk::new(addr.clone(), &receive, &send).await?;
+
+                // listed again for spectators, by the settings we played by.
+                let settings = match peer::Peer::load_match(&mut chat).await? {
+                    Some(state) => replay::Replay::decode(&String::from_utf8_lossy(&state))
+                        .map(|replay| replay.settings)
+                        .unwrap_or(settings),
+                    None => settings,
+                };
+                registry::open(
+                    &addr,
+                    room::Room {
+                        code: code,
+                        settings: settings.clone(),
+                        host: player_name(),
+                        is_open: false,
+                    },
+                )
+                .await?;
+
+                Ok((Box::new(chat), None, true, Some(settings), Some(talk)))
+            }
+
+            Opponent::Queue(addr) => {
+                let player = format!("{:016x}", rand::random::<u64>());
+                let (receive, send) = (protocol::inbox(&player), protocol::outbox(&player));
+                let mut chat = chat::Chat::new(addr.clone(), receive.clone(), send.clone()).await?;
+                // the match server passes lines on, like every move.
+                let talk = chat::Talk::new(addr, &receive, &send).await?;
+
+                chat.announce(
+                    protocol::LOBBY_CHANNEL,
+                    protocol::Message::Queue(player).encode(),
+                )
+                .await?;
+
+                Ok((Box::new(chat), None, false, None, Some(talk)))
+            }
+
+            Opponent::Computer(difficulty) => {
+                let computer = computer::ComputerOpponent::new(difficulty, settings.clone())
+                    .ok_or("the computer couldn't place its fleet")?;
+
+                Ok((Box::new(computer), None, true, Some(settings), None))
+            }
         }
+    }
+
+    // exchange 'Hello's so both sides are subscribed, speak the same version
+    // and play by the host's settings. with a dropped match we first ask the
+    // opponent to resume it.
+    fn handshake(
+        &self,
+        settings: Option<settings::MatchSettings>,
+        dropped: Option<replay::Replay>,
+    ) {
+        let peer = self.session.peer.clone();
+        let handshake = self.handshake.clone();
+        let resigned = self.session.resigned.clone();
+
+        tokio::spawn(async move {
+            let mut peer = peer.lock().await;
+
+            // leaving while we wait lets go of the peer, so we can resign.
+            let result = tokio::select! {
+                result = exchange_hellos(&mut **peer, settings, dropped) => result,
+                _ = resigned.notified() => return,
+            };
+            let result = match result {
+                Ok(result) => result,
+                Err(err) => Handshake::Failed(err.to_string()),
+            };
+
+            *handshake.lock().await = result;
+        });
+    }
 
-        line_points
+    fn apply_settings(&mut self, settings: settings::MatchSettings) {
+        self.geometry =
+            geometry::BoardGeometry::centered(&self.layout, settings.width, settings.height);
+        self.board = engine::Board::new(settings.width, settings.height);
+        self.ships_t = settings.ships();
+        self.curr_ship_index = 0;
+        self.curr_ship = None;
+        self.dragged_part = None;
+        self.settings = Some(settings);
+
+        self.curr_ship = self.get_next_ship();
     }
 
     fn get_next_ship(&mut self) -> Option<ship::Ship> {
@@ -90,28 +393,199 @@ impl InitialState {
             return None;
         }
 
-        let result = Some(ship::Ship::new(self.ships_t[self.curr_ship_index]));
+        let result = Some(ship::Ship::new(
+            self.ships_t[self.curr_ship_index],
+            self.board.width(),
+            self.board.height(),
+        ));
         self.curr_ship_index += 1;
         return result;
     }
 
     fn is_valid_ship(&self) -> bool {
-        if self.curr_ship.is_none() {
-            return false;
+        match self.curr_ship.as_ref() {
+            Some(ship) => {
+                self.board
+                    .check_placement(ship.ship_type, ship.cells())
+                    .is_ok()
+                    && self.board.is_spaced(ship.cells(), self.spacing)
+            }
+            None => false,
+        }
+    }
+
+    fn place_ship(&mut self) {
+        if self.is_valid_ship() {
+            let ship = self.curr_ship.as_ref().unwrap();
+            let _ = self.board.place(ship.ship_type, ship.cells());
+            self.curr_ship = self.get_next_ship();
+            self.sounds.push(audio::Sound::Place);
+        }
+    }
+
+    // takes the current ship into the mouse by the cell at 'c', a click
+    // next to the ship brings it over.
+    fn pick_up(&mut self, c: engine::Coord) {
+        if let Some(ship) = self.curr_ship.as_mut() {
+            let part = match ship.cells().iter().position(|p| *p == c) {
+                Some(part) => part,
+                None => {
+                    ship.move_to(0, &c);
+                    0
+                }
+            };
+            self.dragged_part = Some(part);
+        }
+    }
+
+    // places the ships still left where a player could have, keeping the
+    // spacing. a layout with no room left for the rest is rolled again.
+    fn place_rest(&mut self) {
+        let (board, curr_ship_index, curr_ship) = (
+            self.board.clone(),
+            self.curr_ship_index,
+            self.curr_ship.clone(),
+        );
+        let (width, height) = (self.board.width(), self.board.height());
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..LAYOUT_ATTEMPTS {
+            while let Some(ship_type) = self.curr_ship.as_ref().map(|ship| ship.ship_type) {
+                let is_placed = (0..PLACEMENT_ATTEMPTS).any(|_| {
+                    self.curr_ship = Some(ship::Ship::random(ship_type, width, height, &mut rng));
+                    self.is_valid_ship()
+                });
+                if !is_placed {
+                    break;
+                }
+                self.place_ship();
+            }
+
+            if self.curr_ship.is_none() {
+                self.is_confirmed = false;
+                return;
+            }
+
+            self.board = board.clone();
+            self.curr_ship_index = curr_ship_index;
+            self.curr_ship = curr_ship.clone();
+        }
+
+        println!("<InitialState> no room for the rest of the fleet");
+    }
+
+    // the whole fleet at random again.
+    fn shuffle(&mut self) {
+        if let Some(settings) = self.settings.clone() {
+            self.apply_settings(settings);
+            self.place_rest();
+        }
+    }
+
+    fn set_spacing(&mut self, spacing: i32) {
+        self.spacing = spacing;
+        self.spacing_button.text = format!("spacing {}", spacing);
+    }
+
+    // ships already placed stay where they are, the next ones keep the new spacing.
+    fn cycle_spacing(&mut self) {
+        self.set_spacing((self.spacing + 1) % (engine::MAX_SPACING + 1));
+
+        let path = std::path::Path::new(config::PREFS_FILE);
+        let mut prefs = prefs::Prefs::load(path);
+        prefs.spacing = self.spacing;
+        if let Err(err) = prefs.save(path) {
+            println!(
+                "<InitialState> couldn't save {}: {}",
+                config::PREFS_FILE,
+                err
+            );
         }
+    }
+}
+
+// the first 'Hello' is lost if the opponent isn't subscribed yet,
+// so both sides answer the first 'Hello' they get.
+// an opponent still in our 'dropped' match answers 'Resume' with its history
+// instead, a fresh one with a 'Hello'.
+async fn exchange_hellos(
+    peer: &mut dyn peer::Peer,
+    settings: Option<settings::MatchSettings>,
+    mut dropped: Option<replay::Replay>,
+) -> mini_redis::Result<Handshake> {
+    let mut is_first = None;
+
+    if dropped.is_some() {
+        peer.send_message(&protocol::Message::Resume).await?;
+    }
 
-        // does the current ship overlap already existing ships?
-        for curr_ship_point in self.curr_ship.as_ref().unwrap().body.iter() {
-            for ship in self.ships.iter() {
-                for ship_point in ship.body.iter() {
-                    if *curr_ship_point == *ship_point {
-                        return false;
+    peer.send_message(&protocol::Message::hello(settings.clone()))
+        .await?;
+
+    loop {
+        let frame = match peer.receive().await? {
+            Some(frame) => frame,
+            None => return Err("connection closed during handshake".into()),
+        };
+
+        let hello = match protocol::Message::decode(&frame) {
+            Ok(hello @ protocol::Message::Hello { .. }) => hello,
+
+            Ok(protocol::Message::History(history)) => match dropped.take() {
+                Some(mut replay) => {
+                    println!("<InitialState> resuming the dropped match");
+
+                    // the history is told from the opponent's side.
+                    for event in history.iter().filter_map(|event| event.mirrored()) {
+                        replay.record(event);
                     }
+                    return Ok(Handshake::Resumed(replay));
                 }
+                None => continue,
+            },
+
+            Ok(protocol::Message::Seat(side)) => {
+                is_first = Some(side == engine::Side::First);
+                continue;
+            }
+
+            Ok(protocol::Message::Ping) => continue,
+
+            Ok(other) => {
+                println!("<InitialState> unexpected message: {:?}", other);
+                continue;
+            }
+
+            Err(err) => {
+                println!("<InitialState> protocol error: {}", err);
+                continue;
             }
+        };
+
+        hello.check_version()?;
+
+        let agreed = match (&settings, hello) {
+            (Some(ours), _) => ours.clone(),
+            (
+                None,
+                protocol::Message::Hello {
+                    settings: Some(theirs),
+                    ..
+                },
+            ) => theirs,
+            // another joiner, keep waiting for the host.
+            _ => continue,
+        };
+
+        // the opponent started over, our dropped match is gone for good.
+        if dropped.take().is_some() {
+            peer.save_match(bytes::Bytes::new()).await?;
         }
 
-        return true;
+        peer.send_message(&protocol::Message::hello(settings))
+            .await?;
+
+        return Ok(Handshake::Done(agreed, is_first));
     }
 }
 
@@ -121,73 +595,106 @@ impl state::State for InitialState {
         &mut self,
         event_pump: &mut EventPump,
         next_state: &mut Option<state::NextState>,
+        audio: &audio::Audio,
     ) {
+        let handshake = self.handshake.clone();
+        let handshake = handshake.lock().await;
+
+        let is_handshaken = match &*handshake {
+            Handshake::Waiting => false,
+
+            Handshake::Done(settings, is_first) => {
+                if let Some(is_first) = is_first {
+                    self.session.is_first = *is_first;
+                }
+                if self.settings.is_none() {
+                    self.apply_settings(settings.clone());
+                }
+                true
+            }
+
+            Handshake::Resumed(replay) => {
+                next_state.replace(state::NextState::Update(Box::new(
+                    battle_state::BattleState::resume(replay.clone(), self.session.hand_over()),
+                )));
+                return;
+            }
+
+            Handshake::Failed(err) => {
+                next_state.replace(state::NextState::Update(Box::new(
+                    error_state::ErrorState::new(format!("handshake failed: {}", err), None),
+                )));
+                return;
+            }
+        };
+
+        drop(handshake);
+
         for event in event_pump.poll_iter() {
+            // an open chat gets the keys before the ship does.
+            if let Some(chat_panel) = self.session.chat_panel.as_mut() {
+                if chat_panel.handle_event(&event).await {
+                    continue;
+                }
+            }
+
             match event {
                 Event::Quit { .. } => {
+                    self.session.resign().await;
                     next_state.replace(state::NextState::Quit);
                     return;
                 }
 
                 Event::KeyDown { keycode, .. } => match keycode {
-                    Some(Keycode::Return) => {
-                        if self.is_valid_ship() {
-                            self.ships.push(self.curr_ship.as_ref().unwrap().clone());
-                            self.curr_ship = self.get_next_ship();
-                        }
+                    Some(Keycode::Return) if self.curr_ship.is_none() => self.is_confirmed = true,
+
+                    Some(Keycode::Return) => self.place_ship(),
 
-                        // as placed all ships.
-                        // returns next state 'battle_state'.
-                        if self.curr_ship.is_none() {
-                            let rcv: String;
-                            let snd: String;
-
-                            if self.server_handle.is_some() {
-                                rcv = "player2".to_string();
-                                snd = "player1".to_string();
-                            } else {
-                                rcv = "player1".to_string();
-                                snd = "player2".to_string();
-                            }
-
-                            next_state.replace(state::NextState::Update(Box::new(
-                                battle_state::BattleState::new(
-                                    self.board_lines.clone(),
-                                    self.ships.clone(),
-                                    self.addr.clone(),
-                                    self.server_handle.take(),
-                                    rcv,
-                                    snd,
-                                )
-                                .await
-                                .unwrap(),
-                            )));
-
-                            return;
+                    Some(Keycode::R) => {
+                        if self.curr_ship.is_some() {
+                            self.place_rest();
+                        } else {
+                            self.shuffle();
                         }
                     }
 
+                    Some(Keycode::F) => self.shuffle(),
+
+                    Some(Keycode::C) => self.cycle_spacing(),
+
                     Some(Keycode::W) | Some(Keycode::Up) => {
                         if self.curr_ship.is_some() {
-                            self.curr_ship.as_mut().unwrap().move_xy(&Point::new(0, -1));
+                            self.curr_ship
+                                .as_mut()
+                                .unwrap()
+                                .move_xy(&engine::Coord::new(0, -1));
                         }
                     }
 
                     Some(Keycode::S) | Some(Keycode::Down) => {
                         if self.curr_ship.is_some() {
-                            self.curr_ship.as_mut().unwrap().move_xy(&Point::new(0, 1));
+                            self.curr_ship
+                                .as_mut()
+                                .unwrap()
+                                .move_xy(&engine::Coord::new(0, 1));
                         }
                     }
 
                     Some(Keycode::A) | Some(Keycode::Left) => {
                         if self.curr_ship.is_some() {
-                            self.curr_ship.as_mut().unwrap().move_xy(&Point::new(-1, 0));
+                            self.curr_ship
+                                .as_mut()
+                                .unwrap()
+                                .move_xy(&engine::Coord::new(-1, 0));
                         }
                     }
 
                     Some(Keycode::D) | Some(Keycode::Right) => {
                         if self.curr_ship.is_some() {
-                            self.curr_ship.as_mut().unwrap().move_xy(&Point::new(1, 0));
+                            self.curr_ship
+                                .as_mut()
+                                .unwrap()
+                                .move_xy(&engine::Coord::new(1, 0));
                         }
                     }
 
@@ -204,6 +711,7 @@ impl state::State for InitialState {
                     }
 
                     Some(Keycode::Escape) => {
+                        self.session.resign().await;
                         next_state.replace(state::NextState::Quit);
                         return;
                     }
@@ -213,59 +721,205 @@ impl state::State for InitialState {
                     }
                 },
 
+                Event::MouseButtonDown {
+                    mouse_btn: sdl2::mouse::MouseButton::Left,
+                    x,
+                    y,
+                    ..
+                } => {
+                    if self.random_button.is_click(x, y) && self.curr_ship.is_some() {
+                        self.place_rest();
+                    } else if self.shuffle_button.is_click(x, y) {
+                        self.shuffle();
+                    } else if self.spacing_button.is_click(x, y) {
+                        self.cycle_spacing();
+                    } else if let Some(c) = self.geometry.cell_at(x, y) {
+                        self.pick_up(c);
+                    }
+                }
+
+                Event::MouseMotion { x, y, .. } => {
+                    if let (Some(part), Some(ship)) = (self.dragged_part, self.curr_ship.as_mut()) {
+                        if let Some(c) = self.geometry.cell_at(x, y) {
+                            ship.move_to(part, &c);
+                        }
+                    }
+                }
+
+                // dropping the ship places it, if it fits there.
+                Event::MouseButtonUp {
+                    mouse_btn: sdl2::mouse::MouseButton::Left,
+                    ..
+                } if self.dragged_part.is_some() => {
+                    self.dragged_part = None;
+                    self.place_ship();
+                }
+
+                Event::MouseButtonDown {
+                    mouse_btn: sdl2::mouse::MouseButton::Right,
+                    ..
+                }
+                | Event::MouseWheel { .. }
+                    if self.curr_ship.is_some() =>
+                {
+                    self.curr_ship.as_mut().unwrap().rotate();
+                }
+
                 _ => {}
             }
         }
 
+        // a whole fleet placed at once sounds like one ship.
+        self.sounds.dedup();
+        for sound in self.sounds.drain(..) {
+            audio.play(sound);
+        }
+
+        // as placed all ships and the opponent is there.
+        // returns next state 'battle_state'.
+        if is_handshaken && self.curr_ship.is_none() && self.is_confirmed {
+            let replay = replay::Replay::new(
+                self.settings.clone().unwrap_or_default(),
+                player_name(),
+                self.session.opponent_name.clone(),
+            );
+
+            next_state.replace(state::NextState::Update(Box::new(
+                battle_state::BattleState::new(
+                    self.board.clone(),
+                    self.session.hand_over(),
+                    replay,
+                )
+                .await,
+            )));
+            return;
+        }
+
         next_state.replace(state::NextState::Continue);
     }
 
-    async fn draw(&self, canvas: &mut Canvas<Window>) {
+    async fn draw(
+        &mut self,
+        canvas: &mut Canvas<Window>,
+        text: &text::Text,
+        theme: &theme::Theme,
+        _dt: Duration,
+    ) {
+        let width = self.board.width();
+        let height = self.board.height();
+
+        let status_center = self.layout.bottom_center(config::BOARD_MARGIN as i32 / 2);
+
+        // in the corner, left of the board.
+        if let Some(code) = self.session.room.as_ref() {
+            text.draw(
+                canvas,
+                text::Size::Normal,
+                &format!("room {}", code),
+                10,
+                10,
+                Color::RGBA(255, 255, 255, 255),
+            );
+        }
+        let series = &self.session.series;
+        if series.games() > 0 {
+            text.draw(
+                canvas,
+                text::Size::Small,
+                &format!("series {}-{}", series.wins, series.losses),
+                10,
+                40,
+                Color::RGBA(255, 255, 255, 255),
+            );
+        }
+
+        if width == 0 || height == 0 {
+            text.draw_centered(
+                canvas,
+                text::Size::Normal,
+                "waiting for the match settings...",
+                self.layout.center(),
+                Color::RGBA(255, 255, 255, 255),
+            );
+
+            if let Some(chat_panel) = self.session.chat_panel.as_ref() {
+                chat_panel.draw(canvas, text).await;
+            }
+            return;
+        }
+
+        theme.draw_water(canvas, &self.geometry);
+
         // draw board lines.
         canvas.set_draw_color(Color::RGBA(0, 255, 0, 255));
-        for (p1, p2) in self.board_lines.iter() {
+        for (p1, p2) in self.geometry.lines().iter() {
             canvas.draw_line(*p1, *p2).unwrap()
         }
 
         // draw ships.
-        canvas.set_draw_color(Color::RGBA(0, 255, 0, 200));
-
-        let x_offset: i32 = (config::WINDOW_WIDTH as i32 - config::WINDOW_HEIGHT as i32) / 2;
-        let min_wh: i32 = std::cmp::min(config::WINDOW_WIDTH as i32, config::WINDOW_HEIGHT as i32);
-
-        let x_interval: i32 = min_wh / 10;
-        let y_interval: i32 = min_wh / 10;
+        for ship in self.board.ships().iter() {
+            theme.draw_ship(canvas, &self.geometry, ship.cells());
+        }
 
-        let mut cached_rects: Vec<Rect> = Vec::new();
+        // the current ship, outlined by whether it fits where it is.
+        if let Some(ship) = self.curr_ship.as_ref() {
+            theme.draw_ship(canvas, &self.geometry, ship.cells());
 
-        // cache current ship.
-        if self.curr_ship.is_some() {
-            for point in self.curr_ship.as_ref().unwrap().body.iter() {
-                let rect = Rect::new(
-                    point.x * x_interval + x_offset,
-                    point.y * y_interval,
-                    x_interval as u32,
-                    y_interval as u32,
-                );
-                cached_rects.push(rect);
+            if self.is_valid_ship() {
+                canvas.set_draw_color(Color::RGBA(0, 255, 0, 255));
+            } else {
+                canvas.set_draw_color(Color::RGBA(255, 0, 0, 255));
             }
+            let cached_rects: Vec<Rect> = self.geometry.cell_rects(ship.cells());
+            canvas.draw_rects(&cached_rects[..]).unwrap();
         }
 
-        // cache board ships.
-        for ship in self.ships.iter() {
-            for point in ship.body.iter() {
-                let rect = Rect::new(
-                    point.x * x_interval + x_offset,
-                    point.y * y_interval,
-                    x_interval as u32,
-                    y_interval as u32,
-                );
-                cached_rects.push(rect);
+        text.draw_board_labels(
+            canvas,
+            self.geometry.origin,
+            self.geometry.interval,
+            width,
+            height,
+            Color::RGBA(0, 255, 0, 255),
+        );
+
+        self.random_button.draw(canvas, text);
+        self.shuffle_button.draw(canvas, text);
+        choose_state::draw_caption(canvas, text, self.spacing_button.body, "between ships");
+        self.spacing_button.draw(canvas, text);
+
+        let status = match self.curr_ship.as_ref() {
+            Some(ship) => format!(
+                "place your {} ({} of {}), Q/E or right-click to rotate, R for the rest",
+                ship.ship_type.name(),
+                self.curr_ship_index,
+                self.ships_t.len()
+            ),
+            None if !self.is_confirmed => {
+                "RETURN to sail with this fleet, F to shuffle".to_string()
             }
+            None => "waiting for the opponent...".to_string(),
+        };
+        text.draw_centered(
+            canvas,
+            text::Size::Normal,
+            &status,
+            status_center,
+            Color::RGBA(255, 255, 255, 255),
+        );
+
+        if let Some(chat_panel) = self.session.chat_panel.as_ref() {
+            chat_panel.draw(canvas, text).await;
         }
+    }
 
-        // bash draw.
-        canvas.fill_rects(&cached_rects[..]).unwrap();
-        canvas.draw_rects(&cached_rects[..]).unwrap();
+    fn relayout(&mut self, layout: &layout::Layout) {
+        self.layout = *layout;
+        self.geometry =
+            geometry::BoardGeometry::centered(layout, self.board.width(), self.board.height());
+
+        if let Some(chat_panel) = self.session.chat_panel.as_mut() {
+            chat_panel.relayout(layout);
+        }
     }
 }
@@ -0,0 +1,59 @@
+use sdl2::rect::Point;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+
+use super::config;
+
+// the size of the window as the states lay things out in it. states keep
+// the one they were last given, see 'State::relayout', and work out their
+// boards and buttons from it instead of from fixed numbers.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Layout {
+    // in points, the window's own units. mouse events come in these too.
+    pub width: i32,
+    pub height: i32,
+    // drawable pixels per point, above 1 on high dpi screens.
+    pub scale: f32,
+}
+
+impl Default for Layout {
+    // the size the window opens at, until the first relayout.
+    fn default() -> Layout {
+        Layout {
+            width: config::WINDOW_WIDTH as i32,
+            height: config::WINDOW_HEIGHT as i32,
+            scale: 1.0,
+        }
+    }
+}
+
+impl Layout {
+    // what 'canvas' draws on right now.
+    pub fn of(canvas: &Canvas<Window>) -> Layout {
+        let (window_width, window_height) = canvas.window().size();
+        let (drawable_width, _) = canvas
+            .output_size()
+            .unwrap_or((window_width, window_height));
+
+        let scale = if window_width == 0 {
+            1.0
+        } else {
+            drawable_width as f32 / window_width as f32
+        };
+
+        Layout {
+            width: window_width as i32,
+            height: window_height as i32,
+            scale: scale,
+        }
+    }
+
+    pub fn center(&self) -> Point {
+        Point::new(self.width / 2, self.height / 2)
+    }
+
+    // a line of text 'from_bottom' points above the bottom edge, centered.
+    pub fn bottom_center(&self, from_bottom: i32) -> Point {
+        Point::new(self.width / 2, self.height - from_bottom)
+    }
+}
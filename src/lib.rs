@@ -0,0 +1,13 @@
+// sdl-free parts of the game, shared by the desktop client and any tools.
+pub mod ai;
+pub mod commitment;
+pub mod engine;
+pub mod prefs;
+pub mod protocol;
+pub mod referee;
+pub mod replay;
+pub mod room;
+pub mod series;
+pub mod settings;
+pub mod ship;
+pub mod simulation;
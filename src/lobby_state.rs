@@ -0,0 +1,378 @@
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+use sdl2::pixels::Color;
+use sdl2::rect::Point;
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+use sdl2::EventPump;
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+use super::audio;
+use super::choose_state;
+use super::layout;
+use super::registry;
+use super::room;
+use super::settings;
+use super::state;
+use super::text;
+use super::text_field;
+use super::theme;
+
+// rooms listed at once, the rest wait for a match there to end.
+const MAX_LISTED: usize = 8;
+
+// the rooms of one server, to join an open one, watch a match being
+// played in another, go to one by code or open a room there.
+pub struct LobbyState {
+    layout: layout::Layout,
+    address: String,
+    // what a room we open here is played with.
+    settings: settings::MatchSettings,
+
+    rooms: Vec<room::Room>,
+    room_buttons: Vec<choose_state::Button>,
+
+    code_field: text_field::TextField,
+    join_button: choose_state::Button,
+    watch_button: choose_state::Button,
+    create_button: choose_state::Button,
+    refresh_button: choose_state::Button,
+
+    // a typo in the code or a failed refresh.
+    error: Option<String>,
+}
+
+impl LobbyState {
+    pub async fn new(
+        address: String,
+        settings: settings::MatchSettings,
+    ) -> mini_redis::Result<LobbyState> {
+        let mut state = LobbyState {
+            layout: layout::Layout::default(),
+            address: address,
+            settings: settings,
+
+            rooms: Vec::new(),
+            room_buttons: Vec::new(),
+
+            code_field: text_field::TextField::new(Rect::new(0, 120, 120, 40), String::new()),
+
+            join_button: choose_state::Button {
+                body: Rect::new(0, 120, 70, 40),
+
+                color: Color::RGBA(0, 255, 0, 255),
+
+                text: String::from("join"),
+            },
+
+            watch_button: choose_state::Button {
+                body: Rect::new(0, 120, 70, 40),
+
+                color: Color::RGBA(255, 255, 0, 255),
+
+                text: String::from("watch"),
+            },
+
+            create_button: choose_state::Button {
+                body: Rect::new(0, 210, 200, 40),
+
+                color: Color::RGBA(0, 0, 255, 255),
+
+                text: String::from("open a room"),
+            },
+
+            refresh_button: choose_state::Button {
+                body: Rect::new(0, 300, 200, 40),
+
+                color: Color::RGBA(150, 150, 150, 255),
+
+                text: String::from("refresh"),
+            },
+
+            error: None,
+        };
+        state.place_widgets();
+
+        state.refresh().await?;
+
+        Ok(state)
+    }
+
+    // the rooms on the left of the window's middle, the rest on its right.
+    fn left_x(&self) -> i32 {
+        self.layout.width / 2 - 340
+    }
+
+    fn place_widgets(&mut self) {
+        let right_x = self.layout.width / 2 + 100;
+
+        self.code_field.body.set_x(right_x);
+        self.join_button.body.set_x(right_x + 130);
+        self.watch_button.body.set_x(right_x + 210);
+        self.create_button.body.set_x(right_x);
+        self.refresh_button.body.set_x(right_x);
+
+        let left_x = self.left_x();
+        for button in self.room_buttons.iter_mut() {
+            button.body.set_x(left_x);
+        }
+    }
+
+    async fn refresh(&mut self) -> mini_redis::Result<()> {
+        self.rooms = registry::list(&self.address).await?;
+
+        let left_x = self.left_x();
+        self.room_buttons = self
+            .rooms
+            .iter()
+            .take(MAX_LISTED)
+            .enumerate()
+            .map(|(i, room)| choose_state::Button {
+                body: Rect::new(left_x, 120 + i as i32 * 44, 400, 36),
+
+                // a match being played can only be watched.
+                color: if room.is_open {
+                    Color::RGBA(200, 200, 200, 255)
+                } else {
+                    Color::RGBA(200, 200, 0, 255)
+                },
+
+                text: format!(
+                    "{}   {}   {}x{}, {} ships   {}",
+                    room.code,
+                    if room.is_open { "open" } else { "playing" },
+                    room.settings.width,
+                    room.settings.height,
+                    room.settings.ships().len(),
+                    room.host
+                ),
+            })
+            .collect();
+
+        Ok(())
+    }
+
+    fn check_code(&mut self, code: &str) -> Option<String> {
+        match room::check_code(code) {
+            Ok(code) => Some(code),
+            Err(err) => {
+                println!("<LobbyState> {}", err);
+                self.error = Some(err.to_string());
+                None
+            }
+        }
+    }
+
+    async fn join(&mut self, code: &str) -> Option<Box<dyn state::State>> {
+        let code = self.check_code(code)?;
+
+        Some(
+            choose_state::Connect::Join(self.address.clone(), code, self.settings.clone())
+                .run_or_fail()
+                .await,
+        )
+    }
+
+    async fn watch(&mut self, code: &str) -> Option<Box<dyn state::State>> {
+        let code = self.check_code(code)?;
+
+        Some(
+            choose_state::Connect::Watch(self.address.clone(), code)
+                .run_or_fail()
+                .await,
+        )
+    }
+
+    async fn create(&mut self) -> Option<Box<dyn state::State>> {
+        Some(
+            choose_state::Connect::Create(self.address.clone(), self.settings.clone())
+                .run_or_fail()
+                .await,
+        )
+    }
+
+    async fn refresh_or_complain(&mut self) {
+        self.error = match self.refresh().await {
+            Ok(()) => None,
+            Err(err) => Some(format!("can't list the rooms: {}", err)),
+        };
+    }
+}
+
+#[async_trait(?Send)]
+impl state::State for LobbyState {
+    async fn handle_events(
+        &mut self,
+        event_pump: &mut EventPump,
+        next_state: &mut Option<state::NextState>,
+        _audio: &audio::Audio,
+    ) {
+        for event in event_pump.poll_iter() {
+            // typing a code goes before any shortcut.
+            if self.code_field.handle_event(&event) {
+                continue;
+            }
+
+            let mut new_state = None;
+
+            match event {
+                Event::Quit { .. } => {
+                    next_state.replace(state::NextState::Quit);
+                    return;
+                }
+
+                Event::KeyDown { keycode, .. } => match keycode {
+                    Some(Keycode::Escape) => {
+                        next_state.replace(state::NextState::Quit);
+                        return;
+                    }
+
+                    Some(Keycode::Backspace) => {
+                        next_state.replace(state::NextState::Update(Box::new(
+                            choose_state::ChooseState::new(),
+                        )));
+                        return;
+                    }
+
+                    Some(Keycode::Return) if self.code_field.is_focused => {
+                        let code = self.code_field.value.clone();
+                        new_state = self.join(&code).await;
+                    }
+
+                    Some(Keycode::F5) | Some(Keycode::R) => self.refresh_or_complain().await,
+
+                    _ => {
+                        if let Some(keycode) = keycode {
+                            println!("<LobbyState> unused key: {}", keycode);
+                        }
+                    }
+                },
+
+                Event::MouseButtonDown {
+                    mouse_btn: sdl2::mouse::MouseButton::Left,
+                    x,
+                    y,
+                    ..
+                } => {
+                    self.code_field.is_focused = self.code_field.is_click(x, y);
+
+                    let clicked_room = self
+                        .room_buttons
+                        .iter()
+                        .position(|button| button.is_click(x, y))
+                        .map(|i| (self.rooms[i].code.clone(), self.rooms[i].is_open));
+
+                    if let Some((code, is_open)) = clicked_room {
+                        new_state = if is_open {
+                            self.join(&code).await
+                        } else {
+                            self.watch(&code).await
+                        };
+                    } else if self.join_button.is_click(x, y) {
+                        let code = self.code_field.value.clone();
+                        new_state = self.join(&code).await;
+                    } else if self.watch_button.is_click(x, y) {
+                        let code = self.code_field.value.clone();
+                        new_state = self.watch(&code).await;
+                    } else if self.create_button.is_click(x, y) {
+                        new_state = self.create().await;
+                    } else if self.refresh_button.is_click(x, y) {
+                        self.refresh_or_complain().await;
+                    }
+                }
+
+                _ => {}
+            }
+
+            if let Some(new_state) = new_state {
+                next_state.replace(state::NextState::Update(new_state));
+                return;
+            }
+        }
+
+        next_state.replace(state::NextState::Continue);
+    }
+
+    async fn draw(
+        &mut self,
+        canvas: &mut Canvas<Window>,
+        text: &text::Text,
+        _theme: &theme::Theme,
+        _dt: Duration,
+    ) {
+        let white = Color::RGBA(255, 255, 255, 255);
+
+        text.draw_centered(
+            canvas,
+            text::Size::Large,
+            &format!("rooms at {}", self.address),
+            Point::new(self.layout.width / 2, 50),
+            white,
+        );
+
+        if self.room_buttons.is_empty() {
+            text.draw(
+                canvas,
+                text::Size::Normal,
+                "no rooms, open one or try again later",
+                self.left_x(),
+                120,
+                white,
+            );
+        }
+        for button in self.room_buttons.iter() {
+            button.draw(canvas, text);
+        }
+        if self.rooms.len() > MAX_LISTED {
+            text.draw(
+                canvas,
+                text::Size::Small,
+                &format!("and {} more", self.rooms.len() - MAX_LISTED),
+                self.left_x(),
+                120 + MAX_LISTED as i32 * 44,
+                white,
+            );
+        }
+
+        self.code_field.draw(canvas, text);
+        choose_state::draw_caption(canvas, text, self.code_field.body, "go to a room by code");
+        self.join_button.draw(canvas, text);
+        self.watch_button.draw(canvas, text);
+
+        choose_state::draw_caption(
+            canvas,
+            text,
+            self.create_button.body,
+            &format!("{}x{} board", self.settings.width, self.settings.height),
+        );
+        self.create_button.draw(canvas, text);
+        self.refresh_button.draw(canvas, text);
+
+        if let Some(error) = self.error.as_ref() {
+            text.draw_centered(
+                canvas,
+                text::Size::Small,
+                error,
+                Point::new(self.layout.width / 2, self.layout.height * 5 / 6),
+                Color::RGBA(255, 80, 80, 255),
+            );
+        }
+
+        text.draw_centered(
+            canvas,
+            text::Size::Small,
+            "click a room to join or watch it, R to refresh, BACKSPACE for the lobby, ESC to quit",
+            self.layout.bottom_center(15),
+            white,
+        );
+    }
+
+    fn relayout(&mut self, layout: &layout::Layout) {
+        self.layout = *layout;
+        self.place_widgets();
+    }
+}
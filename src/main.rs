@@ -1,49 +1,201 @@
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
 use sdl2::pixels::Color;
 use sdl2::render::Canvas;
+use sdl2::video::FullscreenType;
 use sdl2::video::Window;
 use sdl2::EventPump;
+use sdl2::EventSubsystem;
 
+use std::time::Instant;
+
+use rusty_battleship::ai;
+use rusty_battleship::commitment;
+use rusty_battleship::engine;
+use rusty_battleship::prefs;
+use rusty_battleship::protocol;
+use rusty_battleship::replay;
+use rusty_battleship::room;
+use rusty_battleship::series;
+use rusty_battleship::settings;
+use rusty_battleship::ship;
+
+mod animation;
+mod audio;
 mod battle_state;
 mod chat;
+mod chat_panel;
 mod choose_state;
+mod clock;
+mod computer;
 mod config;
+mod error_state;
+mod fleet_panel;
+mod geometry;
 mod initial_state;
-mod ship;
+mod layout;
+mod lobby_state;
+mod peer;
+mod registry;
+mod replay_state;
+mod session;
+mod spectate_state;
 mod state;
 mod stats_state;
+mod text;
+mod text_field;
+mod theme;
+mod wake;
 
 struct Game {
     canvas: Canvas<Window>,
     event_pump: EventPump,
+    events: EventSubsystem,
     state: Box<dyn state::State>,
+    text: text::Text,
+    theme: theme::Theme,
+    audio: audio::Audio,
+    clock: clock::Clock,
+    // draws only when something happened, see 'wait_for_change'.
+    is_idle: bool,
+    // the last event or message.
+    woken_at: Instant,
+    // what the state was last laid out for.
+    layout: layout::Layout,
 }
 
+// the events looked at before the state gets them, see 'handle_window_events'.
+const PEEKED_EVENTS: u32 = 64;
+
 impl Game {
     fn new() -> Game {
         let sdl_context: sdl2::Sdl = sdl2::init().unwrap();
         let video_subsystem = sdl_context.video().unwrap();
-        let window = video_subsystem
+        let mut window = video_subsystem
             .window(
                 config::WINDOW_TITLE,
                 config::WINDOW_WIDTH,
                 config::WINDOW_HEIGHT,
             )
             .position_centered()
+            .resizable()
+            .allow_highdpi()
             .build()
             .unwrap();
+        window
+            .set_minimum_size(config::MIN_WINDOW_WIDTH, config::MIN_WINDOW_HEIGHT)
+            .unwrap();
+
+        let prefs = prefs::Prefs::load(std::path::Path::new(config::PREFS_FILE));
 
-        let mut canvas = window.into_canvas().build().unwrap();
+        let mut canvas_builder = window.into_canvas();
+        if prefs.has_vsync {
+            canvas_builder = canvas_builder.present_vsync();
+        }
+        let mut canvas = canvas_builder.build().unwrap();
         canvas.set_blend_mode(sdl2::render::BlendMode::Blend);
 
+        let layout = layout::Layout::of(&canvas);
+        canvas.set_scale(layout.scale, layout.scale).unwrap();
+
+        let events = sdl_context.event().unwrap();
+        wake::register(&events).unwrap();
+
+        let mut state: Box<dyn state::State> = Box::new(choose_state::ChooseState::new());
+        state.relayout(&layout);
+
         Game {
-            state: Box::new(choose_state::ChooseState::new()),
+            state: state,
+            theme: theme::Theme::load(&canvas, &prefs.theme).unwrap(),
+            text: text::Text::new(&canvas).unwrap(),
             canvas: canvas,
             event_pump: sdl_context.event_pump().unwrap(),
+            events: events,
+            audio: audio::Audio::new(&sdl_context, &prefs),
+            clock: clock::Clock::new(prefs.frame_cap),
+            is_idle: prefs.is_idle,
+            woken_at: Instant::now(),
+            layout: layout,
+        }
+    }
+
+    // F11 toggles fullscreen, and the state follows any change of the window's
+    // size or dpi. the events stay queued for the state.
+    fn handle_window_events(&mut self) {
+        self.event_pump.pump_events();
+        let events: Vec<Event> = self.events.peek_events(PEEKED_EVENTS);
+
+        let mut is_window_changed = false;
+        for event in events.iter() {
+            match event {
+                Event::KeyDown {
+                    keycode: Some(Keycode::F11),
+                    repeat: false,
+                    ..
+                } => {
+                    let window = self.canvas.window_mut();
+                    let fullscreen = match window.fullscreen_state() {
+                        FullscreenType::Off => FullscreenType::Desktop,
+                        _ => FullscreenType::Off,
+                    };
+
+                    if let Err(err) = window.set_fullscreen(fullscreen) {
+                        println!("<Game> couldn't switch fullscreen: {}", err);
+                    }
+                }
+
+                Event::Window { .. } => is_window_changed = true,
+
+                _ => {}
+            }
+        }
+
+        if !is_window_changed {
+            return;
+        }
+
+        let layout = layout::Layout::of(&self.canvas);
+        if layout != self.layout {
+            println!(
+                "<Game> window is {}x{} at {}x",
+                layout.width, layout.height, layout.scale
+            );
+
+            self.canvas.set_scale(layout.scale, layout.scale).unwrap();
+            self.state.relayout(&layout);
+            self.layout = layout;
+        }
+    }
+
+    // blocks until an SDL event comes in, network tasks push one for every
+    // message, see 'wake'. what a message changes lands a moment after it,
+    // so frames go on for 'config::IDLE_LINGER' after either. waiting gives
+    // up after 'config::IDLE_REFRESH' to catch what changes on its own, e.g.
+    // a timeout.
+    fn wait_for_change(&mut self) {
+        if self.woken_at.elapsed() < config::IDLE_LINGER {
+            return;
+        }
+
+        // without an event to fill in SDL only waits, the event stays queued
+        // for the state. sdl2 has no safe call that leaves it there.
+        let timeout = config::IDLE_REFRESH.as_millis() as i32;
+        let is_woken = unsafe { sdl2::sys::SDL_WaitEventTimeout(std::ptr::null_mut(), timeout) };
+        if is_woken == 1 {
+            self.woken_at = Instant::now();
         }
     }
 
     async fn run(&mut self) {
         'game_loop: loop {
+            let dt = self.clock.tick().await;
+
+            if self.is_idle && !self.state.is_animating() {
+                self.wait_for_change();
+            }
+
+            self.handle_window_events();
+
             // clear
             self.canvas.set_draw_color(Color::RGB(0, 0, 0));
             self.canvas.clear();
@@ -51,7 +203,7 @@ impl Game {
             let mut next_state: Option<state::NextState> = None;
             let _ = self
                 .state
-                .handle_events(&mut self.event_pump, &mut next_state)
+                .handle_events(&mut self.event_pump, &mut next_state, &self.audio)
                 .await;
 
             match next_state {
@@ -59,6 +211,7 @@ impl Game {
 
                 Some(state::NextState::Update(new_state)) => {
                     self.state = new_state;
+                    self.state.relayout(&self.layout);
                 }
 
                 Some(state::NextState::Continue) => {}
@@ -66,7 +219,9 @@ impl Game {
                 _ => {}
             }
 
-            self.state.draw(&mut self.canvas).await;
+            self.state
+                .draw(&mut self.canvas, &self.text, &self.theme, dt)
+                .await;
 
             // render
             self.canvas.present();
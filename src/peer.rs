@@ -0,0 +1,28 @@
+use async_trait::async_trait;
+use bytes::Bytes;
+use mini_redis::Result;
+
+use super::protocol;
+
+// whoever sits on the other side of the board, over the network or not.
+#[async_trait]
+pub trait Peer: Send {
+    async fn send(&mut self, frame: Bytes) -> Result<()>;
+    async fn receive(&mut self) -> Result<Option<Bytes>>;
+
+    async fn send_message(&mut self, msg: &protocol::Message) -> Result<()> {
+        self.send(msg.encode()).await
+    }
+
+    // our side of a running match, kept on this machine where we can find
+    // it again after dropping out. it holds our fleet and the commitment's
+    // salt, so it must never be sent anywhere. an empty match means there is
+    // nothing to resume.
+    async fn save_match(&mut self, _state: Bytes) -> Result<()> {
+        Ok(())
+    }
+
+    async fn load_match(&mut self) -> Result<Option<Bytes>> {
+        Ok(None)
+    }
+}
@@ -0,0 +1,245 @@
+// lobby, placement, sound, look and frame rate choices remembered between runs, one "<key> <value>" per line.
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::engine;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Prefs {
+    // where we run our server, every interface by default so others can join.
+    pub host_address: String,
+    pub join_address: String,
+    // free cells we keep between our ships, see 'Board::is_spaced'.
+    pub spacing: i32,
+    // percent of the loudest the mixer goes.
+    pub volume: i32,
+    pub is_muted: bool,
+    pub has_music: bool,
+    // a directory of sprites under the themes directory.
+    pub theme: String,
+    // frames per second at most, 0 for as many as the machine manages.
+    pub frame_cap: u32,
+    // waits for the display before showing a frame.
+    pub has_vsync: bool,
+    // draws only when something happened, see 'Game::run'.
+    pub is_idle: bool,
+}
+
+// above this a cap doesn't save anything.
+const MAX_FRAME_CAP: u32 = 1000;
+
+impl Default for Prefs {
+    fn default() -> Prefs {
+        Prefs {
+            host_address: "0.0.0.0:3232".to_string(),
+            join_address: "127.0.0.1:3232".to_string(),
+            spacing: 0,
+            volume: 75,
+            is_muted: false,
+            has_music: true,
+            theme: "default".to_string(),
+            frame_cap: 60,
+            has_vsync: false,
+            is_idle: true,
+        }
+    }
+}
+
+impl Prefs {
+    // a missing or unreadable file gives the defaults.
+    pub fn load(path: &Path) -> Prefs {
+        let mut prefs = Prefs::default();
+
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(_) => return prefs,
+        };
+
+        for line in text.lines() {
+            let (key, value) = match line.find(' ') {
+                Some(i) => (&line[..i], line[i + 1..].trim()),
+                None => continue,
+            };
+
+            // a hand edited file may hold anything.
+            let is_address = check_address(value).is_ok();
+
+            match key {
+                "host_address" if is_address => prefs.host_address = value.to_string(),
+                "join_address" if is_address => prefs.join_address = value.to_string(),
+                "spacing" => match value.parse::<i32>() {
+                    Ok(spacing) if (0..=engine::MAX_SPACING).contains(&spacing) => {
+                        prefs.spacing = spacing
+                    }
+                    _ => {}
+                },
+                "volume" => match value.parse::<i32>() {
+                    Ok(volume) if (0..=100).contains(&volume) => prefs.volume = volume,
+                    _ => {}
+                },
+                "muted" => prefs.is_muted = value.parse().unwrap_or(prefs.is_muted),
+                "music" => prefs.has_music = value.parse().unwrap_or(prefs.has_music),
+                // a name, never a path out of the themes directory.
+                "theme" if is_theme_name(value) => prefs.theme = value.to_string(),
+                "fps" => match value.parse::<u32>() {
+                    Ok(frame_cap) if frame_cap <= MAX_FRAME_CAP => prefs.frame_cap = frame_cap,
+                    _ => {}
+                },
+                "vsync" => prefs.has_vsync = value.parse().unwrap_or(prefs.has_vsync),
+                "idle" => prefs.is_idle = value.parse().unwrap_or(prefs.is_idle),
+                _ => {}
+            }
+        }
+
+        prefs
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(
+            path,
+            format!(
+                "host_address {}\njoin_address {}\nspacing {}\nvolume {}\nmuted {}\nmusic {}\ntheme {}\nfps {}\nvsync {}\nidle {}\n",
+                self.host_address,
+                self.join_address,
+                self.spacing,
+                self.volume,
+                self.is_muted,
+                self.has_music,
+                self.theme,
+                self.frame_cap,
+                self.has_vsync,
+                self.is_idle
+            ),
+        )
+    }
+}
+
+fn is_theme_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum AddressError {
+    MissingPort,
+    MissingHost,
+    BadPort(String),
+}
+
+impl fmt::Display for AddressError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AddressError::MissingPort => write!(f, "address needs a port, e.g. 127.0.0.1:3232"),
+            AddressError::MissingHost => write!(f, "address needs a host, e.g. 127.0.0.1:3232"),
+            AddressError::BadPort(port) => write!(f, "'{}' is not a port", port),
+        }
+    }
+}
+
+impl Error for AddressError {}
+
+// "<host>:<port>", the host being a name or an ip address.
+pub fn check_address(address: &str) -> Result<(), AddressError> {
+    let i = address.rfind(':').ok_or(AddressError::MissingPort)?;
+    let (host, port) = (&address[..i], &address[i + 1..]);
+
+    if host.is_empty() {
+        return Err(AddressError::MissingHost);
+    }
+
+    match port.parse::<u16>() {
+        Ok(port) if port != 0 => Ok(()),
+        _ => Err(AddressError::BadPort(port.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    // a file of its own for every test.
+    fn scratch_file(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "rusty_battleship-{}-{}.prefs",
+            name,
+            std::process::id()
+        ))
+    }
+
+    #[test]
+    fn addresses() {
+        assert_eq!(check_address("127.0.0.1:3232"), Ok(()));
+        assert_eq!(check_address("0.0.0.0:1"), Ok(()));
+        assert_eq!(check_address("localhost:65535"), Ok(()));
+        assert_eq!(check_address("[::1]:3232"), Ok(()));
+
+        assert_eq!(check_address("127.0.0.1"), Err(AddressError::MissingPort));
+        assert_eq!(check_address(""), Err(AddressError::MissingPort));
+        assert_eq!(check_address(":3232"), Err(AddressError::MissingHost));
+        assert_eq!(
+            check_address("127.0.0.1:"),
+            Err(AddressError::BadPort(String::new()))
+        );
+        assert_eq!(
+            check_address("127.0.0.1:0"),
+            Err(AddressError::BadPort("0".to_string()))
+        );
+        assert_eq!(
+            check_address("127.0.0.1:65536"),
+            Err(AddressError::BadPort("65536".to_string()))
+        );
+        assert_eq!(
+            check_address("127.0.0.1:http"),
+            Err(AddressError::BadPort("http".to_string()))
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let path = scratch_file("round-trip");
+        let prefs = Prefs {
+            host_address: "192.168.1.2:4000".to_string(),
+            join_address: "example.com:3232".to_string(),
+            spacing: 2,
+            volume: 25,
+            is_muted: true,
+            has_music: false,
+            theme: "dark_sea".to_string(),
+            frame_cap: 0,
+            has_vsync: true,
+            is_idle: false,
+        };
+
+        prefs.save(&path).unwrap();
+        assert_eq!(Prefs::load(&path), prefs);
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn bad_values_keep_the_defaults() {
+        let path = scratch_file("bad-values");
+        fs::write(
+            &path,
+            "host_address nowhere\njoin_address :3232\nspacing 9\nvolume -5\nmuted maybe\n\
+             theme ../../etc\nfps 100000\nidle\nunknown key\n",
+        )
+        .unwrap();
+
+        assert_eq!(Prefs::load(&path), Prefs::default());
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn missing_file() {
+        assert_eq!(Prefs::load(&scratch_file("missing")), Prefs::default());
+    }
+}
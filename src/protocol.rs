@@ -0,0 +1,432 @@
+// messages exchanged between two players.
+// every frame is a single line of space separated words, e.g. "SHOT 3 4".
+
+use bytes::Bytes;
+use std::error::Error;
+use std::fmt;
+
+use super::commitment::{self, Fleet};
+use super::engine::{Coord, ShipType, ShotResult, Side};
+use super::replay::{self, Event};
+use super::settings::MatchSettings;
+
+// bump this whenever a frame changes shape.
+pub const VERSION: u32 = 6;
+
+// where players queue on a match server, see 'Message::Queue'.
+pub const LOBBY_CHANNEL: &str = "lobby";
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Message {
+    // only the host sends settings, the joiner plays by them.
+    Hello {
+        version: u32,
+        settings: Option<MatchSettings>,
+    },
+    Shot(Coord),
+    ShotResult(ShotResult),
+    GameOver,
+    Resign,
+    Chat(String),
+    Ping,
+    // a player who dropped out asks for the match so far.
+    Resume,
+    // every shot of the match, as the sender saw it.
+    History(Vec<Event>),
+    // the digest of our fleet, sent before the first shot.
+    Commit(String),
+    // the salt and fleet behind the digest, sent once the match is over.
+    Reveal(String, Fleet),
+    // asks a match server for an opponent, the player listens on its 'inbox'
+    // and talks on its 'outbox'.
+    Queue(String),
+    // a match server telling a player which side it plays, the first shoots first.
+    Seat(Side),
+    // a match server telling a player they lost the match for breaking its
+    // rules, and which one. the opponent gets a 'Resign'.
+    Foul(String),
+    // another game against the same opponent, sent once the match is over.
+    // it starts when both asked, whoever shot second shoots first then.
+    Rematch,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ProtocolError {
+    Empty,
+    NotUtf8,
+    UnknownMessage(String),
+    MissingField(&'static str),
+    BadField(&'static str, String),
+    VersionMismatch { ours: u32, theirs: u32 },
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProtocolError::Empty => write!(f, "empty frame"),
+            ProtocolError::NotUtf8 => write!(f, "frame is not valid utf-8"),
+            ProtocolError::UnknownMessage(tag) => write!(f, "unknown message '{}'", tag),
+            ProtocolError::MissingField(field) => write!(f, "missing field '{}'", field),
+            ProtocolError::BadField(field, value) => {
+                write!(f, "bad value '{}' for field '{}'", value, field)
+            }
+            ProtocolError::VersionMismatch { ours, theirs } => write!(
+                f,
+                "protocol version mismatch (ours {}, theirs {})",
+                ours, theirs
+            ),
+        }
+    }
+}
+
+impl Error for ProtocolError {}
+
+impl Message {
+    pub fn hello(settings: Option<MatchSettings>) -> Message {
+        Message::Hello {
+            version: VERSION,
+            settings,
+        }
+    }
+
+    pub fn encode(&self) -> Bytes {
+        let frame = match self {
+            Message::Hello {
+                version,
+                settings: None,
+            } => format!("HELLO {}", version),
+            Message::Hello {
+                version,
+                settings: Some(settings),
+            } => format!("HELLO {} {}", version, encode_settings(settings)),
+            Message::Shot(c) => format!("SHOT {} {}", c.x, c.y),
+            Message::ShotResult(result) => format!("RESULT {}", encode_result(*result)),
+            Message::GameOver => "GAMEOVER".to_string(),
+            Message::Resign => "RESIGN".to_string(),
+            Message::Chat(text) => format!("CHAT {}", text),
+            Message::Ping => "PING".to_string(),
+            Message::Resume => "RESUME".to_string(),
+            Message::History(events) => {
+                let events: Vec<String> = events.iter().map(replay::encode_event).collect();
+                format!("HISTORY {}", events.join(";"))
+            }
+            Message::Commit(digest) => format!("COMMIT {}", digest),
+            Message::Reveal(salt, fleet) => {
+                format!("REVEAL {} {}", salt, commitment::encode_fleet(fleet))
+            }
+            Message::Queue(player) => format!("QUEUE {}", player),
+            Message::Seat(Side::First) => "SEAT 1".to_string(),
+            Message::Seat(Side::Second) => "SEAT 2".to_string(),
+            Message::Foul(reason) => format!("FOUL {}", reason),
+            Message::Rematch => "REMATCH".to_string(),
+        };
+
+        Bytes::from(frame)
+    }
+
+    pub fn decode(frame: &[u8]) -> Result<Message, ProtocolError> {
+        let frame = std::str::from_utf8(frame).map_err(|_| ProtocolError::NotUtf8)?;
+
+        // chat text is free form, so only split off the tag.
+        let (tag, rest) = match frame.find(' ') {
+            Some(i) => (&frame[..i], &frame[i + 1..]),
+            None => (frame, ""),
+        };
+        let mut words = rest.split_whitespace();
+
+        let message = match tag {
+            "" => return Err(ProtocolError::Empty),
+
+            "HELLO" => {
+                let version = parse_field(words.next(), "version")?;
+
+                // a newer peer may have changed the rest, let 'check_version' say so.
+                if version != VERSION {
+                    return Ok(Message::Hello {
+                        version,
+                        settings: None,
+                    });
+                }
+
+                let settings = match words.next() {
+                    Some(width) => Some(decode_settings(width, &mut words)?),
+                    None => None,
+                };
+
+                Message::Hello { version, settings }
+            }
+
+            "SHOT" => Message::Shot(Coord::new(
+                parse_field(words.next(), "x")?,
+                parse_field(words.next(), "y")?,
+            )),
+
+            "RESULT" => Message::ShotResult(decode_result(&mut words)?),
+
+            "GAMEOVER" => Message::GameOver,
+            "RESIGN" => Message::Resign,
+            "CHAT" => return Ok(Message::Chat(rest.to_string())),
+            "FOUL" => return Ok(Message::Foul(rest.to_string())),
+            "PING" => Message::Ping,
+            "RESUME" => Message::Resume,
+
+            "HISTORY" => {
+                let mut events: Vec<Event> = Vec::new();
+                for event in rest.split(';').filter(|event| !event.trim().is_empty()) {
+                    match replay::decode_event(event)? {
+                        Event::Place(..) => {
+                            return Err(ProtocolError::BadField("history", event.to_string()))
+                        }
+                        event => events.push(event),
+                    }
+                }
+                Message::History(events)
+            }
+
+            "COMMIT" => Message::Commit(parse_field(words.next(), "digest")?),
+
+            "REVEAL" => {
+                let salt = parse_field(words.next(), "salt")?;
+                let fleet = match rest.find(' ') {
+                    Some(i) => commitment::decode_fleet(&rest[i + 1..])?,
+                    None => Vec::new(),
+                };
+                Message::Reveal(salt, fleet)
+            }
+
+            "QUEUE" => Message::Queue(parse_field(words.next(), "player")?),
+
+            "SEAT" => match words.next() {
+                Some("1") => Message::Seat(Side::First),
+                Some("2") => Message::Seat(Side::Second),
+                Some(other) => return Err(ProtocolError::BadField("seat", other.to_string())),
+                None => return Err(ProtocolError::MissingField("seat")),
+            },
+
+            "REMATCH" => Message::Rematch,
+
+            other => return Err(ProtocolError::UnknownMessage(other.to_string())),
+        };
+
+        Ok(message)
+    }
+
+    // the first frame a peer sends must be a compatible 'Hello'.
+    pub fn check_version(&self) -> Result<(), ProtocolError> {
+        match self {
+            Message::Hello { version, .. } if *version != VERSION => {
+                Err(ProtocolError::VersionMismatch {
+                    ours: VERSION,
+                    theirs: *version,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+// the channels a queued player listens and talks on.
+pub fn inbox(player: &str) -> String {
+    format!("{}:in", player)
+}
+
+pub fn outbox(player: &str) -> String {
+    format!("{}:out", player)
+}
+
+// 'Chat' lines travel here, next to the match on 'channel' but never in it,
+// so a line can't be taken for a move.
+pub fn talk_channel(channel: &str) -> String {
+    format!("{}:talk", channel)
+}
+
+pub(crate) fn parse_field<T: std::str::FromStr>(
+    word: Option<&str>,
+    field: &'static str,
+) -> Result<T, ProtocolError> {
+    let word = word.ok_or(ProtocolError::MissingField(field))?;
+    word.parse()
+        .map_err(|_| ProtocolError::BadField(field, word.to_string()))
+}
+
+// "MISS", "HIT" or "SUNK <type>".
+pub(crate) fn encode_result(result: ShotResult) -> String {
+    match result {
+        ShotResult::Miss => "MISS".to_string(),
+        ShotResult::Hit => "HIT".to_string(),
+        ShotResult::Sunk(ship_type) => format!("SUNK {}", ship_type.name()),
+    }
+}
+
+pub(crate) fn decode_result<'a>(
+    words: &mut impl Iterator<Item = &'a str>,
+) -> Result<ShotResult, ProtocolError> {
+    match words.next() {
+        Some("MISS") => Ok(ShotResult::Miss),
+        Some("HIT") => Ok(ShotResult::Hit),
+        Some("SUNK") => {
+            let name = words.next().ok_or(ProtocolError::MissingField("ship"))?;
+            let ship_type = ShipType::from_name(name)
+                .ok_or_else(|| ProtocolError::BadField("ship", name.to_string()))?;
+            Ok(ShotResult::Sunk(ship_type))
+        }
+        Some(other) => Err(ProtocolError::BadField("result", other.to_string())),
+        None => Err(ProtocolError::MissingField("result")),
+    }
+}
+
+// "<width> <height> <type>:<count>,<type>:<count>,..."
+pub(crate) fn encode_settings(settings: &MatchSettings) -> String {
+    let fleet: Vec<String> = settings
+        .fleet
+        .iter()
+        .map(|(ship_type, count)| format!("{}:{}", ship_type.name(), count))
+        .collect();
+
+    format!("{} {} {}", settings.width, settings.height, fleet.join(","))
+}
+
+pub(crate) fn decode_settings<'a>(
+    width: &str,
+    words: &mut impl Iterator<Item = &'a str>,
+) -> Result<MatchSettings, ProtocolError> {
+    let width = parse_field(Some(width), "width")?;
+    let height = parse_field(words.next(), "height")?;
+
+    let mut fleet: Vec<(ShipType, usize)> = Vec::new();
+    for entry in words
+        .next()
+        .ok_or(ProtocolError::MissingField("fleet"))?
+        .split(',')
+    {
+        let mut parts = entry.splitn(2, ':');
+        let name = parts.next().unwrap_or("");
+        let ship_type = ShipType::from_name(name)
+            .ok_or_else(|| ProtocolError::BadField("ship", name.to_string()))?;
+        fleet.push((ship_type, parse_field(parts.next(), "count")?));
+    }
+
+    let settings = MatchSettings {
+        width,
+        height,
+        fleet,
+    };
+    settings
+        .validate()
+        .map_err(|err| ProtocolError::BadField("settings", err.to_string()))?;
+
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(message: Message) {
+        assert_eq!(Message::decode(&message.encode()), Ok(message));
+    }
+
+    #[test]
+    fn every_message_round_trips() {
+        let fleet = vec![
+            (
+                ShipType::PatrolBoat,
+                vec![Coord::new(0, 0), Coord::new(1, 0)],
+            ),
+            (
+                ShipType::Destroyer,
+                vec![Coord::new(5, 2), Coord::new(5, 3), Coord::new(5, 4)],
+            ),
+        ];
+
+        for settings in MatchSettings::presets() {
+            round_trip(Message::hello(Some(settings)));
+        }
+        round_trip(Message::hello(None));
+        round_trip(Message::Shot(Coord::new(3, 4)));
+        round_trip(Message::ShotResult(ShotResult::Miss));
+        round_trip(Message::ShotResult(ShotResult::Hit));
+        round_trip(Message::ShotResult(ShotResult::Sunk(ShipType::Carrier)));
+        round_trip(Message::GameOver);
+        round_trip(Message::Resign);
+        round_trip(Message::Chat("hello  there, captain".to_string()));
+        round_trip(Message::Ping);
+        round_trip(Message::Resume);
+        round_trip(Message::History(vec![
+            Event::Shot(Coord::new(1, 2), ShotResult::Hit),
+            Event::Incoming(Coord::new(3, 4), ShotResult::Sunk(ShipType::Submarine)),
+        ]));
+        round_trip(Message::History(Vec::new()));
+        round_trip(Message::Commit("abc123".to_string()));
+        round_trip(Message::Reveal("00ff".to_string(), fleet));
+        round_trip(Message::Reveal("00ff".to_string(), Vec::new()));
+        round_trip(Message::Queue("player-1".to_string()));
+        round_trip(Message::Seat(Side::First));
+        round_trip(Message::Seat(Side::Second));
+        round_trip(Message::Foul("bad shot: Repeated".to_string()));
+        round_trip(Message::Rematch);
+    }
+
+    #[test]
+    fn malformed_frames() {
+        let decode = |frame: &str| Message::decode(frame.as_bytes());
+
+        assert_eq!(decode(""), Err(ProtocolError::Empty));
+        assert_eq!(Message::decode(&[0xff, 0xfe]), Err(ProtocolError::NotUtf8));
+        assert_eq!(
+            decode("FIRE 1 2"),
+            Err(ProtocolError::UnknownMessage("FIRE".to_string()))
+        );
+        assert_eq!(decode("SHOT 1"), Err(ProtocolError::MissingField("y")));
+        assert_eq!(
+            decode("SHOT a 2"),
+            Err(ProtocolError::BadField("x", "a".to_string()))
+        );
+        assert_eq!(decode("RESULT"), Err(ProtocolError::MissingField("result")));
+        assert_eq!(
+            decode("RESULT SUNK Canoe"),
+            Err(ProtocolError::BadField("ship", "Canoe".to_string()))
+        );
+        assert_eq!(decode("SEAT"), Err(ProtocolError::MissingField("seat")));
+        assert_eq!(
+            decode("SEAT 3"),
+            Err(ProtocolError::BadField("seat", "3".to_string()))
+        );
+        assert_eq!(decode("COMMIT"), Err(ProtocolError::MissingField("digest")));
+        assert!(decode("HISTORY P Carrier 0 0").is_err());
+        assert!(decode("REVEAL 00ff Carrier 0").is_err());
+    }
+
+    #[test]
+    fn bad_settings() {
+        let hello =
+            |settings: &str| Message::decode(format!("HELLO {} {}", VERSION, settings).as_bytes());
+
+        assert_eq!(hello("10 10"), Err(ProtocolError::MissingField("fleet")));
+        assert!(hello("10 10 Canoe:1").is_err());
+        assert!(hello("3 3 PatrolBoat:1").is_err());
+        assert!(hello("10 10 Carrier:0").is_err());
+        // a count this large must be turned down before any ship is built.
+        assert!(hello(&format!("10 10 Carrier:{}", usize::MAX)).is_err());
+    }
+
+    #[test]
+    fn versions() {
+        let newer = Message::decode(format!("HELLO {} whatever", VERSION + 1).as_bytes());
+        assert_eq!(
+            newer,
+            Ok(Message::Hello {
+                version: VERSION + 1,
+                settings: None,
+            })
+        );
+        assert_eq!(
+            newer.unwrap().check_version(),
+            Err(ProtocolError::VersionMismatch {
+                ours: VERSION,
+                theirs: VERSION + 1,
+            })
+        );
+        assert_eq!(Message::hello(None).check_version(), Ok(()));
+    }
+}
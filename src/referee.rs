@@ -0,0 +1,386 @@
+// the rules a match server holds both players to. it never sees a fleet
+// before the reveal, so it checks turns and shots as they are played, counts
+// the answers against the size of the fleet and checks every answer once the
+// fleets are revealed.
+
+use std::error::Error;
+use std::fmt;
+
+use super::commitment::{self, Cheat};
+use super::engine::{Coord, ShotError, ShotResult, Side, TargetBoard};
+use super::protocol::Message;
+use super::settings::MatchSettings;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Foul {
+    // a shot that breaks the rules, e.g. out of turn or repeated.
+    Shot(ShotError),
+    // an answer nobody asked for.
+    UnexpectedAnswer,
+    // a commitment after the first shot could be fitted to the answers.
+    LateCommitment,
+    // more misses than the board has water, the fleet must have been hit.
+    TooManyMisses,
+    // a game over with some of the fleet never hit.
+    NotDefeated,
+    // the revealed fleet doesn't hold up, only known once the match is over.
+    Cheated(Cheat),
+}
+
+impl fmt::Display for Foul {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Foul::Shot(err) => write!(f, "bad shot: {:?}", err),
+            Foul::UnexpectedAnswer => write!(f, "answered a shot nobody fired"),
+            Foul::LateCommitment => write!(f, "committed to a fleet after the first shot"),
+            Foul::TooManyMisses => write!(f, "answered more misses than there is water"),
+            Foul::NotDefeated => write!(f, "gave up the game with ships afloat"),
+            Foul::Cheated(cheat) => write!(f, "{}", cheat),
+        }
+    }
+}
+
+impl Error for Foul {}
+
+pub struct Referee {
+    settings: MatchSettings,
+    // shots each side fired, to catch repeats.
+    targets: [TargetBoard; 2],
+    // answers each side gave, in order.
+    answers: [Vec<(Coord, ShotResult)>; 2],
+    // hits and misses each side answered, against the cells of the fleet.
+    hits: [usize; 2],
+    misses: [usize; 2],
+    commitments: [Option<String>; 2],
+    is_revealed: [bool; 2],
+    // the side whose whole fleet was hit, it owes us its reveal.
+    defeated: Option<Side>,
+
+    turn: Side,
+    // a shot still waiting for its answer.
+    pending: Option<Coord>,
+    is_over: bool,
+    is_resigned: bool,
+}
+
+impl Referee {
+    pub fn new(settings: MatchSettings) -> Referee {
+        let target = TargetBoard::new(settings.width, settings.height);
+
+        Referee {
+            settings,
+            targets: [target.clone(), target],
+            answers: [Vec::new(), Vec::new()],
+            hits: [0, 0],
+            misses: [0, 0],
+            commitments: [None, None],
+            is_revealed: [false, false],
+            defeated: None,
+            turn: Side::First,
+            pending: None,
+            is_over: false,
+            is_resigned: false,
+        }
+    }
+
+    // nothing left to check, both fleets are revealed or someone gave up.
+    pub fn is_done(&self) -> bool {
+        self.is_resigned || self.is_revealed.iter().all(|is_revealed| *is_revealed)
+    }
+
+    // the side that lost and has yet to reveal its fleet. the server gives
+    // it a while before it forfeits.
+    pub fn owes_reveal(&self) -> Option<Side> {
+        match self.defeated {
+            Some(side) if !self.is_revealed[side.index()] && !self.is_resigned => Some(side),
+            _ => None,
+        }
+    }
+
+    // checks a message 'side' sent before it's passed on to the other side.
+    pub fn check(&mut self, side: Side, msg: &Message) -> Result<(), Foul> {
+        let i = side.index();
+
+        match msg {
+            Message::Commit(digest) => {
+                let is_started = self.answers.iter().any(|answers| !answers.is_empty());
+                if self.commitments[i].is_some() || is_started {
+                    return Err(Foul::LateCommitment);
+                }
+                self.commitments[i] = Some(digest.clone());
+            }
+
+            Message::Shot(shot) => {
+                if self.is_over {
+                    return Err(Foul::Shot(ShotError::GameOver));
+                }
+                if self.turn != side || self.pending.is_some() {
+                    return Err(Foul::Shot(ShotError::NotYourTurn));
+                }
+                self.targets[i].check_shot(*shot).map_err(Foul::Shot)?;
+
+                self.pending = Some(*shot);
+            }
+
+            Message::ShotResult(result) => {
+                let shot = match self.pending {
+                    Some(shot) if self.turn != side => shot,
+                    _ => return Err(Foul::UnexpectedAnswer),
+                };
+
+                self.targets[self.turn.index()].record(shot, *result);
+                self.answers[i].push((shot, *result));
+                self.pending = None;
+                self.turn = side;
+
+                let fleet_cells = self.settings.fleet_cells();
+                match result {
+                    ShotResult::Miss => self.misses[i] += 1,
+                    _ => self.hits[i] += 1,
+                }
+                let water_cells =
+                    (self.settings.width * self.settings.height) as usize - fleet_cells;
+                if self.misses[i] > water_cells {
+                    return Err(Foul::TooManyMisses);
+                }
+
+                // no more shots, whether or not they admit it.
+                if self.hits[i] >= fleet_cells {
+                    self.is_over = true;
+                    self.defeated = Some(side);
+                }
+            }
+
+            Message::GameOver if self.defeated != Some(side) => return Err(Foul::NotDefeated),
+
+            Message::Resign => {
+                self.is_over = true;
+                self.is_resigned = true;
+            }
+
+            Message::Reveal(salt, fleet) => {
+                self.is_revealed[i] = true;
+
+                commitment::verify_answers(
+                    self.commitments[i].as_deref(),
+                    &self.settings,
+                    salt,
+                    fleet,
+                    &self.answers[i],
+                )
+                .map_err(Foul::Cheated)?;
+            }
+
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commitment::Commitment;
+    use crate::engine::ShipType;
+
+    // one patrol boat on a 5x5 board, two hits sink the fleet.
+    fn referee() -> Referee {
+        Referee::new(MatchSettings {
+            width: 5,
+            height: 5,
+            fleet: vec![(ShipType::PatrolBoat, 1)],
+        })
+    }
+
+    fn shoot(referee: &mut Referee, side: Side, x: i32, y: i32, result: ShotResult) {
+        assert_eq!(
+            referee.check(side, &Message::Shot(Coord::new(x, y))),
+            Ok(())
+        );
+        assert_eq!(
+            referee.check(side.other(), &Message::ShotResult(result)),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn turn_order() {
+        let mut referee = referee();
+
+        assert_eq!(
+            referee.check(Side::Second, &Message::Shot(Coord::new(0, 0))),
+            Err(Foul::Shot(ShotError::NotYourTurn))
+        );
+        assert_eq!(
+            referee.check(Side::First, &Message::Shot(Coord::new(0, 0))),
+            Ok(())
+        );
+        // no second shot before the answer.
+        assert_eq!(
+            referee.check(Side::First, &Message::Shot(Coord::new(1, 0))),
+            Err(Foul::Shot(ShotError::NotYourTurn))
+        );
+        // the shooter can't answer its own shot.
+        assert_eq!(
+            referee.check(Side::First, &Message::ShotResult(ShotResult::Miss)),
+            Err(Foul::UnexpectedAnswer)
+        );
+        assert_eq!(
+            referee.check(Side::Second, &Message::ShotResult(ShotResult::Miss)),
+            Ok(())
+        );
+
+        // the turn passes whatever the answer.
+        assert_eq!(
+            referee.check(Side::First, &Message::Shot(Coord::new(1, 0))),
+            Err(Foul::Shot(ShotError::NotYourTurn))
+        );
+        shoot(&mut referee, Side::Second, 0, 0, ShotResult::Hit);
+        shoot(&mut referee, Side::First, 1, 0, ShotResult::Miss);
+    }
+
+    #[test]
+    fn bad_shots() {
+        let mut referee = referee();
+
+        assert_eq!(
+            referee.check(Side::Second, &Message::ShotResult(ShotResult::Miss)),
+            Err(Foul::UnexpectedAnswer)
+        );
+        assert_eq!(
+            referee.check(Side::First, &Message::Shot(Coord::new(5, 0))),
+            Err(Foul::Shot(ShotError::OutOfBounds))
+        );
+        shoot(&mut referee, Side::First, 0, 0, ShotResult::Miss);
+        shoot(&mut referee, Side::Second, 0, 0, ShotResult::Miss);
+        assert_eq!(
+            referee.check(Side::First, &Message::Shot(Coord::new(0, 0))),
+            Err(Foul::Shot(ShotError::Repeated))
+        );
+    }
+
+    #[test]
+    fn late_commitment() {
+        let mut referee = referee();
+
+        assert_eq!(
+            referee.check(Side::First, &Message::Commit("a".to_string())),
+            Ok(())
+        );
+        assert_eq!(
+            referee.check(Side::First, &Message::Commit("b".to_string())),
+            Err(Foul::LateCommitment)
+        );
+        shoot(&mut referee, Side::First, 0, 0, ShotResult::Miss);
+        assert_eq!(
+            referee.check(Side::Second, &Message::Commit("c".to_string())),
+            Err(Foul::LateCommitment)
+        );
+    }
+
+    #[test]
+    fn too_many_misses() {
+        let mut referee = referee();
+
+        // 23 cells of water, the 24th miss can't be true.
+        for i in 0..23 {
+            shoot(&mut referee, Side::First, i % 5, i / 5, ShotResult::Miss);
+            shoot(&mut referee, Side::Second, i % 5, i / 5, ShotResult::Miss);
+        }
+        assert_eq!(
+            referee.check(Side::First, &Message::Shot(Coord::new(3, 4))),
+            Ok(())
+        );
+        assert_eq!(
+            referee.check(Side::Second, &Message::ShotResult(ShotResult::Miss)),
+            Err(Foul::TooManyMisses)
+        );
+    }
+
+    #[test]
+    fn defeat_and_reveal() {
+        let mut referee = referee();
+        let fleet = vec![(
+            ShipType::PatrolBoat,
+            vec![Coord::new(0, 0), Coord::new(1, 0)],
+        )];
+        let commitment = Commitment::new(&fleet, &mut rand::thread_rng());
+
+        assert_eq!(
+            referee.check(Side::Second, &Message::Commit(commitment.digest.clone())),
+            Ok(())
+        );
+        assert_eq!(
+            referee.check(Side::First, &Message::GameOver),
+            Err(Foul::NotDefeated)
+        );
+
+        shoot(&mut referee, Side::First, 0, 0, ShotResult::Hit);
+        shoot(&mut referee, Side::Second, 4, 4, ShotResult::Miss);
+        assert_eq!(referee.owes_reveal(), None);
+        shoot(
+            &mut referee,
+            Side::First,
+            1,
+            0,
+            ShotResult::Sunk(ShipType::PatrolBoat),
+        );
+
+        // the match is over whether the loser says so or not.
+        assert_eq!(referee.owes_reveal(), Some(Side::Second));
+        assert_eq!(
+            referee.check(Side::First, &Message::Shot(Coord::new(2, 2))),
+            Err(Foul::Shot(ShotError::GameOver))
+        );
+        assert_eq!(
+            referee.check(Side::First, &Message::GameOver),
+            Err(Foul::NotDefeated)
+        );
+        assert_eq!(referee.check(Side::Second, &Message::GameOver), Ok(()));
+
+        assert_eq!(
+            referee.check(Side::Second, &Message::Reveal(commitment.salt, fleet)),
+            Ok(())
+        );
+        assert_eq!(referee.owes_reveal(), None);
+        assert!(!referee.is_done());
+    }
+
+    #[test]
+    fn cheated() {
+        let mut referee = referee();
+        let fleet = vec![(
+            ShipType::PatrolBoat,
+            vec![Coord::new(0, 0), Coord::new(1, 0)],
+        )];
+        let commitment = Commitment::new(&fleet, &mut rand::thread_rng());
+
+        assert_eq!(
+            referee.check(Side::Second, &Message::Commit(commitment.digest.clone())),
+            Ok(())
+        );
+        shoot(&mut referee, Side::First, 0, 0, ShotResult::Miss);
+
+        assert_eq!(
+            referee.check(Side::Second, &Message::Reveal(commitment.salt, fleet)),
+            Err(Foul::Cheated(Cheat::WrongAnswer {
+                shot: Coord::new(0, 0),
+                said: ShotResult::Miss,
+                was: ShotResult::Hit,
+            }))
+        );
+    }
+
+    #[test]
+    fn resign() {
+        let mut referee = referee();
+
+        assert_eq!(referee.check(Side::Second, &Message::Resign), Ok(()));
+        assert!(referee.is_done());
+        assert_eq!(
+            referee.check(Side::First, &Message::Shot(Coord::new(0, 0))),
+            Err(Foul::Shot(ShotError::GameOver))
+        );
+    }
+}
@@ -0,0 +1,125 @@
+// the rooms of a server, see 'room'. nothing locks the registry,
+// two rooms opened at the same moment may lose one from the listing,
+// a room's players don't depend on it.
+
+use mini_redis::{client, Result};
+
+use super::engine::Side;
+use super::protocol;
+use super::room;
+
+async fn read(client: &mut client::Client) -> Result<Vec<room::Room>> {
+    let text = client.get(room::REGISTRY_KEY).await?.unwrap_or_default();
+    Ok(room::decode_registry(&String::from_utf8_lossy(&text)))
+}
+
+async fn write(client: &mut client::Client, rooms: &[room::Room]) -> Result<()> {
+    client
+        .set(room::REGISTRY_KEY, room::encode_registry(rooms).into())
+        .await
+}
+
+// whether anyone listens on 'channel', answered with a ping they ignore.
+async fn is_listening(client: &mut client::Client, channel: &str) -> Result<bool> {
+    let listeners = client
+        .publish(channel, protocol::Message::Ping.encode())
+        .await?;
+    Ok(listeners > 0)
+}
+
+// rooms whose host is still there, the rest are dropped from the registry.
+pub async fn list(address: &str) -> Result<Vec<room::Room>> {
+    let mut client = client::connect(address).await?;
+
+    let rooms = read(&mut client).await?;
+    let mut open: Vec<room::Room> = Vec::with_capacity(rooms.len());
+
+    for room in rooms.iter() {
+        let host = room::channel(&room.code, Side::Second);
+        if is_listening(&mut client, &host).await? {
+            open.push(room.clone());
+        }
+    }
+
+    if open.len() != rooms.len() {
+        write(&mut client, &open).await?;
+    }
+
+    Ok(open)
+}
+
+// a code no room on the server uses yet.
+pub async fn free_code(address: &str) -> Result<String> {
+    let taken: Vec<String> = list(address)
+        .await?
+        .into_iter()
+        .map(|room| room.code)
+        .collect();
+
+    loop {
+        let code = room::new_code(&mut rand::thread_rng());
+        if !taken.contains(&code) {
+            return Ok(code);
+        }
+    }
+}
+
+pub async fn open(address: &str, new_room: room::Room) -> Result<()> {
+    let mut client = client::connect(address).await?;
+
+    let mut rooms = read(&mut client).await?;
+    rooms.retain(|room| room.code != new_room.code);
+    rooms.push(new_room);
+
+    write(&mut client, &rooms).await
+}
+
+// a room with a guest isn't open anymore, it stays listed for spectators.
+pub async fn start(address: &str, code: &str) -> Result<()> {
+    let mut client = client::connect(address).await?;
+
+    let mut rooms = read(&mut client).await?;
+    for room in rooms.iter_mut().filter(|room| room.code == code) {
+        room.is_open = false;
+    }
+
+    write(&mut client, &rooms).await
+}
+
+pub async fn find(address: &str, code: &str) -> Result<room::Room> {
+    list(address)
+        .await?
+        .into_iter()
+        .find(|room| room.code == code)
+        .ok_or_else(|| format!("there is no room {}", code).into())
+}
+
+// joining a room that is gone or already has a guest would only
+// confuse its players. a guest who dropped out may take their seat again.
+pub async fn check_seat(address: &str, code: &str) -> Result<()> {
+    let mut client = client::connect(address).await?;
+
+    if !is_listening(&mut client, &room::channel(code, Side::Second)).await? {
+        return Err(format!("there is no room {}", code).into());
+    }
+    if is_listening(&mut client, &room::channel(code, Side::First)).await? {
+        return Err(format!("room {} is full", code).into());
+    }
+
+    Ok(())
+}
+
+// the host's seat of a room whose match they dropped out of, see
+// 'check_seat'. it only makes sense while the guest waits for them.
+pub async fn check_host_seat(address: &str, code: &str) -> Result<()> {
+    let mut client = client::connect(address).await?;
+
+    if is_listening(&mut client, &room::channel(code, Side::Second)).await? {
+        return Err(format!("room {} already has its host", code).into());
+    }
+    if !is_listening(&mut client, &room::channel(code, Side::First)).await? {
+        return Err(format!("nobody is left in room {}", code).into());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,482 @@
+// everything that happened in a match, from one player's side, so it can
+// be saved after the match and stepped through later.
+// a replay file is one event per line, e.g. "SHOT 3 4 SUNK Destroyer".
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::commitment::Commitment;
+use super::engine::{Board, Coord, ShipType, ShotResult, TargetBoard};
+use super::protocol::{self, ProtocolError};
+use super::settings::MatchSettings;
+
+// bump this whenever a line changes shape.
+pub const VERSION: u32 = 1;
+
+pub const EXTENSION: &str = "replay";
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Event {
+    // one of the recording player's ships.
+    Place(ShipType, Vec<Coord>),
+    // the recording player shot at the opponent.
+    Shot(Coord, ShotResult),
+    // the opponent shot at the recording player.
+    Incoming(Coord, ShotResult),
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    VersionMismatch { ours: u32, theirs: u32 },
+    BadLine(usize, ProtocolError),
+    MissingHeader(&'static str),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::Io(err) => write!(f, "{}", err),
+            ReplayError::VersionMismatch { ours, theirs } => write!(
+                f,
+                "replay version mismatch (ours {}, theirs {})",
+                ours, theirs
+            ),
+            ReplayError::BadLine(line, err) => write!(f, "line {}: {}", line, err),
+            ReplayError::MissingHeader(header) => write!(f, "missing header '{}'", header),
+        }
+    }
+}
+
+impl Error for ReplayError {}
+
+impl From<io::Error> for ReplayError {
+    fn from(err: io::Error) -> ReplayError {
+        ReplayError::Io(err)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Replay {
+    // seconds since the unix epoch when the match started.
+    pub timestamp: u64,
+    pub settings: MatchSettings,
+    pub player: String,
+    pub opponent: String,
+    // ours with its salt, and the digest the opponent committed to.
+    pub commitment: Option<Commitment>,
+    pub their_commitment: Option<String>,
+    pub events: Vec<Event>,
+}
+
+impl Replay {
+    pub fn new(settings: MatchSettings, player: String, opponent: String) -> Replay {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        Replay {
+            timestamp,
+            settings,
+            player,
+            opponent,
+            commitment: None,
+            their_commitment: None,
+            events: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, event: Event) {
+        self.events.push(event);
+    }
+
+    // both boards of the recording player once the first 'step' events happened.
+    pub fn position(&self, step: usize) -> (Board, TargetBoard) {
+        let mut board = Board::new(self.settings.width, self.settings.height);
+        let mut targets = TargetBoard::new(self.settings.width, self.settings.height);
+
+        for event in self.events.iter().take(step) {
+            match event {
+                Event::Place(ship_type, cells) => {
+                    let _ = board.place(*ship_type, cells);
+                }
+                Event::Shot(c, result) => targets.record(*c, *result),
+                Event::Incoming(c, _) => {
+                    let _ = board.receive_shot(*c);
+                }
+            }
+        }
+
+        (board, targets)
+    }
+
+    pub fn encode(&self) -> String {
+        let mut lines = vec![
+            format!("REPLAY {}", VERSION),
+            format!("TIME {}", self.timestamp),
+            format!("SETTINGS {}", protocol::encode_settings(&self.settings)),
+            format!("PLAYER {}", self.player),
+            format!("OPPONENT {}", self.opponent),
+        ];
+
+        if let Some(commitment) = &self.commitment {
+            lines.push(format!("COMMIT {} {}", commitment.salt, commitment.digest));
+        }
+        if let Some(digest) = &self.their_commitment {
+            lines.push(format!("THEIRS {}", digest));
+        }
+
+        for event in self.events.iter() {
+            lines.push(encode_event(event));
+        }
+
+        lines.join("\n") + "\n"
+    }
+
+    pub fn decode(text: &str) -> Result<Replay, ReplayError> {
+        let mut version = None;
+        let mut timestamp = None;
+        let mut settings = None;
+        let mut player = None;
+        let mut opponent = None;
+        let mut commitment = None;
+        let mut their_commitment = None;
+        let mut events: Vec<Event> = Vec::new();
+
+        for (i, line) in text.lines().enumerate() {
+            let bad_line = |err| ReplayError::BadLine(i + 1, err);
+
+            // names are free form, so only split off the tag.
+            let (tag, rest) = match line.find(' ') {
+                Some(i) => (&line[..i], &line[i + 1..]),
+                None => (line, ""),
+            };
+            let mut words = rest.split_whitespace();
+
+            match tag {
+                "" => {}
+
+                "REPLAY" => {
+                    let theirs =
+                        protocol::parse_field(words.next(), "version").map_err(bad_line)?;
+                    if theirs != VERSION {
+                        return Err(ReplayError::VersionMismatch {
+                            ours: VERSION,
+                            theirs,
+                        });
+                    }
+                    version = Some(theirs);
+                }
+
+                "TIME" => {
+                    timestamp =
+                        Some(protocol::parse_field(words.next(), "time").map_err(bad_line)?);
+                }
+
+                "SETTINGS" => {
+                    let width = words
+                        .next()
+                        .ok_or(ProtocolError::MissingField("width"))
+                        .map_err(bad_line)?;
+                    settings =
+                        Some(protocol::decode_settings(width, &mut words).map_err(bad_line)?);
+                }
+
+                "PLAYER" => player = Some(rest.to_string()),
+                "OPPONENT" => opponent = Some(rest.to_string()),
+
+                "COMMIT" => {
+                    commitment = Some(Commitment {
+                        salt: protocol::parse_field(words.next(), "salt").map_err(bad_line)?,
+                        digest: protocol::parse_field(words.next(), "digest").map_err(bad_line)?,
+                    });
+                }
+
+                "THEIRS" => {
+                    their_commitment =
+                        Some(protocol::parse_field(words.next(), "digest").map_err(bad_line)?);
+                }
+
+                _ => events.push(decode_event(line).map_err(bad_line)?),
+            }
+        }
+
+        version.ok_or(ReplayError::MissingHeader("REPLAY"))?;
+
+        Ok(Replay {
+            timestamp: timestamp.ok_or(ReplayError::MissingHeader("TIME"))?,
+            settings: settings.ok_or(ReplayError::MissingHeader("SETTINGS"))?,
+            player: player.ok_or(ReplayError::MissingHeader("PLAYER"))?,
+            opponent: opponent.ok_or(ReplayError::MissingHeader("OPPONENT"))?,
+            commitment,
+            their_commitment,
+            events,
+        })
+    }
+
+    // writes "<dir>/<timestamp>.replay" and returns its path. a match that
+    // started in the same second, e.g. a quick rematch, is already there,
+    // so the next ones get "<timestamp>-2.replay", "<timestamp>-3.replay"...
+    pub fn save(&self, dir: &Path) -> Result<PathBuf, ReplayError> {
+        fs::create_dir_all(dir)?;
+
+        let mut n = 1;
+        loop {
+            let name = match n {
+                1 => format!("{}.{}", self.timestamp, EXTENSION),
+                n => format!("{}-{}.{}", self.timestamp, n, EXTENSION),
+            };
+            let path = dir.join(name);
+
+            match fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(mut file) => {
+                    file.write_all(self.encode().as_bytes())?;
+                    return Ok(path);
+                }
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => n += 1,
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Replay, ReplayError> {
+        Replay::decode(&fs::read_to_string(path)?)
+    }
+}
+
+impl Event {
+    // the same event as the other player saw it, placements stay private.
+    pub fn mirrored(&self) -> Option<Event> {
+        match self {
+            Event::Place(..) => None,
+            Event::Shot(c, result) => Some(Event::Incoming(*c, *result)),
+            Event::Incoming(c, result) => Some(Event::Shot(*c, *result)),
+        }
+    }
+}
+
+// one line of a replay, also used by the protocol to send the match so far.
+pub(crate) fn encode_event(event: &Event) -> String {
+    match event {
+        Event::Place(ship_type, cells) => {
+            let cells: Vec<String> = cells.iter().map(|c| format!("{} {}", c.x, c.y)).collect();
+            format!("PLACE {} {}", ship_type.name(), cells.join(" "))
+        }
+        Event::Shot(c, result) => {
+            format!("SHOT {} {} {}", c.x, c.y, protocol::encode_result(*result))
+        }
+        Event::Incoming(c, result) => format!(
+            "INCOMING {} {} {}",
+            c.x,
+            c.y,
+            protocol::encode_result(*result)
+        ),
+    }
+}
+
+pub(crate) fn decode_event(line: &str) -> Result<Event, ProtocolError> {
+    let mut words = line.split_whitespace();
+
+    match words.next() {
+        Some("PLACE") => {
+            let name = words.next().ok_or(ProtocolError::MissingField("ship"))?;
+            let ship_type = ShipType::from_name(name)
+                .ok_or_else(|| ProtocolError::BadField("ship", name.to_string()))?;
+
+            let mut cells: Vec<Coord> = Vec::new();
+            while let Some(x) = words.next() {
+                cells.push(Coord::new(
+                    protocol::parse_field(Some(x), "x")?,
+                    protocol::parse_field(words.next(), "y")?,
+                ));
+            }
+
+            Ok(Event::Place(ship_type, cells))
+        }
+
+        Some(tag @ "SHOT") | Some(tag @ "INCOMING") => {
+            let c = Coord::new(
+                protocol::parse_field(words.next(), "x")?,
+                protocol::parse_field(words.next(), "y")?,
+            );
+            let result = protocol::decode_result(&mut words)?;
+
+            if tag == "SHOT" {
+                Ok(Event::Shot(c, result))
+            } else {
+                Ok(Event::Incoming(c, result))
+            }
+        }
+
+        Some(other) => Err(ProtocolError::UnknownMessage(other.to_string())),
+        None => Err(ProtocolError::Empty),
+    }
+}
+
+// the most recently started match saved in 'dir'.
+pub fn latest(dir: &Path) -> Option<PathBuf> {
+    fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().and_then(|ext| ext.to_str()) == Some(EXTENSION))
+        .filter_map(|path| {
+            // "<timestamp>" or "<timestamp>-<n>", see 'Replay::save'.
+            let stem = path.file_stem()?.to_str()?;
+            let (timestamp, n) = match stem.split_once('-') {
+                Some((timestamp, n)) => (timestamp, n.parse::<u32>().ok()?),
+                None => (stem, 1),
+            };
+            let timestamp: u64 = timestamp.parse().ok()?;
+            Some(((timestamp, n), path))
+        })
+        .max_by_key(|(key, _)| *key)
+        .map(|(_, path)| path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replay() -> Replay {
+        let mut replay = Replay::new(
+            MatchSettings::default(),
+            "me".to_string(),
+            "the other one".to_string(),
+        );
+        replay.timestamp = 1_600_000_000;
+        replay.commitment = Some(Commitment {
+            salt: "00ff".to_string(),
+            digest: "abcd".to_string(),
+        });
+        replay.their_commitment = Some("dcba".to_string());
+
+        replay.record(Event::Place(
+            ShipType::PatrolBoat,
+            vec![Coord::new(0, 0), Coord::new(1, 0)],
+        ));
+        replay.record(Event::Shot(Coord::new(4, 4), ShotResult::Miss));
+        replay.record(Event::Incoming(Coord::new(0, 0), ShotResult::Hit));
+        replay.record(Event::Shot(
+            Coord::new(5, 5),
+            ShotResult::Sunk(ShipType::Destroyer),
+        ));
+        replay
+    }
+
+    // a fresh directory of its own for every test.
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("rusty_battleship-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn round_trip() {
+        let replay = replay();
+        assert_eq!(Replay::decode(&replay.encode()).unwrap(), replay);
+
+        let mut bare = replay.clone();
+        bare.commitment = None;
+        bare.their_commitment = None;
+        bare.events.clear();
+        assert_eq!(Replay::decode(&bare.encode()).unwrap(), bare);
+    }
+
+    #[test]
+    fn bad_files() {
+        let text = replay().encode();
+
+        let newer = text.replacen(&format!("REPLAY {}", VERSION), "REPLAY 99", 1);
+        assert!(matches!(
+            Replay::decode(&newer),
+            Err(ReplayError::VersionMismatch { theirs: 99, .. })
+        ));
+
+        let headless: String = text
+            .lines()
+            .skip(1)
+            .map(|line| format!("{}\n", line))
+            .collect();
+        assert!(matches!(
+            Replay::decode(&headless),
+            Err(ReplayError::MissingHeader("REPLAY"))
+        ));
+
+        let broken = text + "SHOT 1\n";
+        assert!(matches!(
+            Replay::decode(&broken),
+            Err(ReplayError::BadLine(_, ProtocolError::MissingField("y")))
+        ));
+    }
+
+    #[test]
+    fn position() {
+        let (board, targets) = replay().position(3);
+
+        assert_eq!(board.ships().len(), 1);
+        assert_eq!(board.hits(), &[Coord::new(0, 0)]);
+        assert_eq!(targets.misses(), &[Coord::new(4, 4)]);
+        assert!(targets.hits().is_empty());
+    }
+
+    #[test]
+    fn mirrored() {
+        let shot = Event::Shot(Coord::new(1, 2), ShotResult::Hit);
+        let incoming = Event::Incoming(Coord::new(1, 2), ShotResult::Hit);
+
+        assert_eq!(shot.mirrored(), Some(incoming.clone()));
+        assert_eq!(incoming.mirrored(), Some(shot));
+        assert_eq!(
+            Event::Place(ShipType::PatrolBoat, Vec::new()).mirrored(),
+            None
+        );
+    }
+
+    #[test]
+    fn saves_never_collide() {
+        let dir = scratch_dir("saves");
+        let replay = replay();
+
+        let first = replay.save(&dir).unwrap();
+        let second = replay.save(&dir).unwrap();
+        let third = replay.save(&dir).unwrap();
+
+        assert_eq!(first, dir.join("1600000000.replay"));
+        assert_eq!(second, dir.join("1600000000-2.replay"));
+        assert_eq!(third, dir.join("1600000000-3.replay"));
+        assert_eq!(Replay::load(&first).unwrap(), replay);
+        assert_eq!(Replay::load(&third).unwrap(), replay);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn latest_match() {
+        let dir = scratch_dir("latest");
+        assert_eq!(latest(&dir), None);
+
+        let mut replay = replay();
+        replay.save(&dir).unwrap();
+        // the rematch started in the same second.
+        let rematch = replay.save(&dir).unwrap();
+        assert_eq!(latest(&dir), Some(rematch));
+
+        replay.timestamp += 1;
+        let newest = replay.save(&dir).unwrap();
+        fs::write(dir.join("notes.txt"), "").unwrap();
+        fs::write(dir.join("not-a-number.replay"), "").unwrap();
+        assert_eq!(latest(&dir), Some(newest));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
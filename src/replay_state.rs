@@ -0,0 +1,225 @@
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+use sdl2::EventPump;
+
+use std::time::Duration;
+use std::vec::Vec;
+
+use async_trait::async_trait;
+
+use super::animation;
+use super::audio;
+use super::battle_state;
+use super::choose_state;
+use super::config;
+use super::engine;
+use super::geometry;
+use super::layout;
+use super::replay;
+use super::state;
+use super::text;
+use super::theme;
+
+pub struct ReplayState {
+    layout: layout::Layout,
+    // the player's fleet on the left, their shots on the right.
+    boards: (geometry::BoardGeometry, geometry::BoardGeometry),
+    replay: replay::Replay,
+    fleet: Vec<engine::ShipType>,
+
+    // how many events of the replay have happened.
+    step: usize,
+    // the shot of a step forward plays out, any other move stops it.
+    animations: animation::Animations,
+}
+
+impl ReplayState {
+    pub fn new(replay: replay::Replay) -> ReplayState {
+        let layout = layout::Layout::default();
+
+        ReplayState {
+            layout: layout,
+            boards: geometry::BoardGeometry::side_by_side(
+                &layout,
+                replay.settings.width,
+                replay.settings.height,
+            ),
+            fleet: replay.settings.ships(),
+            replay: replay,
+
+            step: 0,
+            animations: animation::Animations::new(),
+        }
+    }
+
+    fn go_to(&mut self, step: usize) {
+        let step = std::cmp::min(step, self.replay.events.len());
+
+        self.animations.clear();
+        if step == self.step + 1 {
+            self.animate(self.step);
+        }
+
+        self.step = step;
+    }
+
+    // the shot of event 'i', on the board it hit, as it happened in battle.
+    fn animate(&mut self, i: usize) {
+        let (my_board, my_targets) = self.replay.position(i + 1);
+        let (fleet_board, target_board) = self.boards;
+
+        match &self.replay.events[i] {
+            replay::Event::Shot(c, result) => {
+                let ship = match result {
+                    engine::ShotResult::Sunk(ship_type) => my_targets.sunk_cells(*c, *ship_type),
+                    _ => None,
+                };
+                self.animations.shot(target_board, *c, *result, ship);
+            }
+
+            replay::Event::Incoming(c, result) => {
+                let ship = match result {
+                    engine::ShotResult::Sunk(_) => {
+                        my_board.ship_at(*c).map(|ship| ship.cells().to_vec())
+                    }
+                    _ => None,
+                };
+                self.animations.shot(fleet_board, *c, *result, ship);
+            }
+
+            replay::Event::Place(..) => {}
+        }
+    }
+
+    // the cells touched by the event that led to the current step, on the
+    // board they are on.
+    fn last_cells(&self) -> Vec<Rect> {
+        let (fleet_board, target_board) = &self.boards;
+
+        match self.step.checked_sub(1).map(|i| &self.replay.events[i]) {
+            Some(replay::Event::Place(_, cells)) => fleet_board.cell_rects(cells),
+            Some(replay::Event::Incoming(c, _)) => vec![fleet_board.cell_rect(*c)],
+            Some(replay::Event::Shot(c, _)) => vec![target_board.cell_rect(*c)],
+            None => Vec::new(),
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl state::State for ReplayState {
+    async fn handle_events(
+        &mut self,
+        event_pump: &mut EventPump,
+        next_state: &mut Option<state::NextState>,
+        _audio: &audio::Audio,
+    ) {
+        for event in event_pump.poll_iter() {
+            match event {
+                Event::Quit { .. } => {
+                    next_state.replace(state::NextState::Quit);
+                    return;
+                }
+
+                Event::KeyDown { keycode, .. } => match keycode {
+                    Some(Keycode::Escape) => {
+                        next_state.replace(state::NextState::Quit);
+                        return;
+                    }
+
+                    Some(Keycode::Backspace) => {
+                        next_state.replace(state::NextState::Update(Box::new(
+                            choose_state::ChooseState::new(),
+                        )));
+                        return;
+                    }
+
+                    Some(Keycode::D) | Some(Keycode::Right) => {
+                        self.go_to(self.step + 1);
+                    }
+
+                    Some(Keycode::A) | Some(Keycode::Left) => {
+                        self.go_to(self.step.saturating_sub(1));
+                    }
+
+                    Some(Keycode::Home) => {
+                        self.go_to(0);
+                    }
+
+                    Some(Keycode::End) => {
+                        self.go_to(self.replay.events.len());
+                    }
+
+                    _ => {
+                        if let Some(keycode) = keycode {
+                            println!("<ReplayState> unused key: {}", keycode);
+                        }
+                    }
+                },
+
+                _ => {}
+            }
+        }
+
+        next_state.replace(state::NextState::Continue);
+    }
+
+    async fn draw(
+        &mut self,
+        canvas: &mut Canvas<Window>,
+        text: &text::Text,
+        theme: &theme::Theme,
+        dt: Duration,
+    ) {
+        let (my_board, my_targets) = self.replay.position(self.step);
+
+        battle_state::draw_boards(
+            canvas,
+            text,
+            theme,
+            &self.boards,
+            &my_board,
+            &my_targets,
+            &self.fleet,
+        );
+
+        self.animations.draw(canvas, theme, dt);
+
+        // outline what just happened.
+        let cache: Vec<Rect> = self.last_cells();
+
+        canvas.set_draw_color(Color::RGBA(255, 255, 0, 255));
+        canvas.draw_rects(&cache[..]).unwrap();
+
+        text.draw_centered(
+            canvas,
+            text::Size::Normal,
+            &format!(
+                "{} vs {}, step {}/{}",
+                self.replay.player,
+                self.replay.opponent,
+                self.step,
+                self.replay.events.len()
+            ),
+            self.layout.bottom_center(config::BOARD_MARGIN as i32 / 2),
+            Color::RGBA(255, 255, 0, 255),
+        );
+    }
+
+    fn relayout(&mut self, layout: &layout::Layout) {
+        self.layout = *layout;
+        self.boards = geometry::BoardGeometry::side_by_side(
+            layout,
+            self.replay.settings.width,
+            self.replay.settings.height,
+        );
+        self.animations.clear();
+    }
+
+    fn is_animating(&self) -> bool {
+        self.animations.is_running()
+    }
+}
@@ -0,0 +1,201 @@
+// rooms namespace the channels of a match, so one server hosts many.
+// a server keeps its rooms under one key, one room per line,
+// e.g. "KXRT open 10 10 Carrier:1,Battleship:1 pedro".
+
+use rand::Rng;
+use std::error::Error;
+use std::fmt;
+
+use super::engine::Side;
+use super::protocol;
+use super::settings::MatchSettings;
+
+// where a server lists its rooms.
+pub const REGISTRY_KEY: &str = "rooms";
+
+pub const CODE_LENGTH: usize = 4;
+
+// no 'I' or 'O', codes are read out loud and typed in.
+const CODE_CHARS: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ";
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Room {
+    pub code: String,
+    pub settings: MatchSettings,
+    // who opened the room, free form.
+    pub host: String,
+    // waiting for a guest, otherwise the match is on and can be watched.
+    pub is_open: bool,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum CodeError {
+    WrongLength(usize),
+    BadChar(char),
+}
+
+impl fmt::Display for CodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CodeError::WrongLength(length) => {
+                write!(f, "a room code has {} letters, not {}", CODE_LENGTH, length)
+            }
+            CodeError::BadChar(c) => write!(f, "'{}' can't be in a room code", c),
+        }
+    }
+}
+
+impl Error for CodeError {}
+
+pub fn new_code(rng: &mut impl Rng) -> String {
+    (0..CODE_LENGTH)
+        .map(|_| CODE_CHARS[rng.gen_range(0, CODE_CHARS.len())] as char)
+        .collect()
+}
+
+// the code as it's used in channel names, typed codes may be lowercase.
+pub fn check_code(code: &str) -> Result<String, CodeError> {
+    let code = code.trim().to_uppercase();
+
+    if code.chars().count() != CODE_LENGTH {
+        return Err(CodeError::WrongLength(code.chars().count()));
+    }
+    if let Some(c) = code.chars().find(|c| !CODE_CHARS.contains(&(*c as u8))) {
+        return Err(CodeError::BadChar(c));
+    }
+
+    Ok(code)
+}
+
+// the channel the player in 'seat' talks on, the host sits first.
+pub fn channel(code: &str, seat: Side) -> String {
+    match seat {
+        Side::First => format!("{}:player1", code),
+        Side::Second => format!("{}:player2", code),
+    }
+}
+
+// players repeat everything they send on 'channel' here for spectators,
+// who so never count as listeners of the players' channels.
+pub fn watch_channel(channel: &str) -> String {
+    format!("{}:watch", channel)
+}
+
+pub fn encode_registry(rooms: &[Room]) -> String {
+    let lines: Vec<String> = rooms
+        .iter()
+        .map(|room| {
+            format!(
+                "{} {} {} {}",
+                room.code,
+                if room.is_open { "open" } else { "playing" },
+                protocol::encode_settings(&room.settings),
+                room.host
+            )
+        })
+        .collect();
+
+    lines.join("\n")
+}
+
+// rooms that don't decode are left out, anyone may write the registry.
+pub fn decode_registry(text: &str) -> Vec<Room> {
+    text.lines().filter_map(decode_room).collect()
+}
+
+fn decode_room(line: &str) -> Option<Room> {
+    let mut words = line.splitn(6, ' ');
+
+    let code = check_code(words.next()?).ok()?;
+    let is_open = match words.next()? {
+        "open" => true,
+        "playing" => false,
+        _ => return None,
+    };
+    let width = words.next()?;
+    let mut settings_words = vec![words.next()?, words.next()?].into_iter();
+    let settings = protocol::decode_settings(width, &mut settings_words).ok()?;
+    let host = words.next().unwrap_or("").to_string();
+
+    Some(Room {
+        code,
+        settings,
+        host,
+        is_open,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rooms() -> Vec<Room> {
+        let presets = MatchSettings::presets();
+
+        vec![
+            Room {
+                code: "KXRT".to_string(),
+                settings: presets[0].clone(),
+                host: "pedro".to_string(),
+                is_open: true,
+            },
+            Room {
+                code: "ABCD".to_string(),
+                settings: presets[2].clone(),
+                host: "the host with spaces".to_string(),
+                is_open: false,
+            },
+            Room {
+                code: "ZZZZ".to_string(),
+                settings: presets[1].clone(),
+                host: String::new(),
+                is_open: true,
+            },
+        ]
+    }
+
+    #[test]
+    fn codes() {
+        assert_eq!(check_code("KXRT"), Ok("KXRT".to_string()));
+        assert_eq!(check_code(" kxrt\n"), Ok("KXRT".to_string()));
+        assert_eq!(check_code("KXR"), Err(CodeError::WrongLength(3)));
+        assert_eq!(check_code("KXRTS"), Err(CodeError::WrongLength(5)));
+        assert_eq!(check_code(""), Err(CodeError::WrongLength(0)));
+        assert_eq!(check_code("KXOT"), Err(CodeError::BadChar('O')));
+        assert_eq!(check_code("KX1T"), Err(CodeError::BadChar('1')));
+        assert_eq!(check_code("KXÉT"), Err(CodeError::BadChar('É')));
+    }
+
+    #[test]
+    fn new_codes_check() {
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..100 {
+            let code = new_code(&mut rng);
+            assert_eq!(check_code(&code), Ok(code));
+        }
+    }
+
+    #[test]
+    fn registry_round_trips() {
+        assert_eq!(decode_registry(&encode_registry(&rooms())), rooms());
+        assert_eq!(decode_registry(&encode_registry(&[])), Vec::new());
+    }
+
+    #[test]
+    fn bad_rooms_are_left_out() {
+        let good = encode_registry(&rooms()[..1]);
+        let lines = [
+            "KXOT open 10 10 Carrier:1 bad code".to_string(),
+            "ABCD waiting 10 10 Carrier:1 bad status".to_string(),
+            "ABCD open 10 10".to_string(),
+            "ABCD open 99 99 Carrier:1 bad settings".to_string(),
+            format!("ABCD open 10 10 Carrier:{} huge fleet", usize::MAX),
+            "ABCD".to_string(),
+            String::new(),
+            good,
+        ];
+
+        assert_eq!(decode_registry(&lines.join("\n")), rooms()[..1].to_vec());
+    }
+}
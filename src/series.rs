@@ -0,0 +1,90 @@
+// the running score of the games played against one opponent in a row.
+// whoever wins more than half of 'best_of' games takes the series.
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Series {
+    pub best_of: u32,
+    pub wins: u32,
+    pub losses: u32,
+}
+
+impl Series {
+    pub fn new(best_of: u32) -> Series {
+        Series {
+            best_of,
+            wins: 0,
+            losses: 0,
+        }
+    }
+
+    pub fn record(&mut self, is_won: bool) {
+        if is_won {
+            self.wins += 1;
+        } else {
+            self.losses += 1;
+        }
+    }
+
+    pub fn games(&self) -> u32 {
+        self.wins + self.losses
+    }
+
+    pub fn is_decided(&self) -> bool {
+        self.wins * 2 > self.best_of || self.losses * 2 > self.best_of
+    }
+
+    // the series the next game counts for, a decided one starts over.
+    pub fn next(&self) -> Series {
+        if self.is_decided() {
+            Series::new(self.best_of)
+        } else {
+            *self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scoring() {
+        let mut series = Series::new(3);
+        assert_eq!(series.games(), 0);
+
+        series.record(true);
+        series.record(false);
+        assert_eq!((series.wins, series.losses, series.games()), (1, 1, 2));
+        assert!(!series.is_decided());
+
+        series.record(true);
+        assert_eq!((series.wins, series.losses, series.games()), (2, 1, 3));
+        assert!(series.is_decided());
+    }
+
+    #[test]
+    fn decided_early() {
+        let mut series = Series::new(5);
+
+        series.record(false);
+        series.record(false);
+        assert!(!series.is_decided());
+        series.record(false);
+        assert!(series.is_decided());
+
+        // a single game decides a best of 1.
+        let mut single = Series::new(1);
+        single.record(true);
+        assert!(single.is_decided());
+    }
+
+    #[test]
+    fn next_series() {
+        let mut series = Series::new(3);
+        series.record(true);
+        assert_eq!(series.next(), series);
+
+        series.record(true);
+        assert_eq!(series.next(), Series::new(3));
+    }
+}
@@ -0,0 +1,54 @@
+use std::sync::Arc;
+use tokio::sync::{Mutex, Notify};
+use tokio::task::JoinHandle;
+
+use super::chat_panel;
+use super::peer;
+use super::protocol;
+use super::series;
+
+pub type ServerHandle = JoinHandle<mini_redis::Result<()>>;
+
+// the line to one opponent and whatever outlives a single game against
+// them, handed from state to state until we go back to the lobby.
+pub struct Session {
+    pub peer: Arc<Mutex<Box<dyn peer::Peer>>>,
+    // keeps our server around for as long as we play on it.
+    pub server_handle: Option<ServerHandle>,
+    pub opponent_name: String,
+    // the code to tell the opponent, if we play in a room.
+    pub room: Option<String>,
+    pub is_first: bool,
+    // the computer doesn't chat.
+    pub chat_panel: Option<chat_panel::ChatPanel>,
+    pub series: series::Series,
+    // whatever waits on the peer lets go of it once we resign, see 'resign'.
+    pub resigned: Arc<Notify>,
+}
+
+impl Session {
+    // the session for the next state, this one keeps only what can be shared.
+    pub fn hand_over(&mut self) -> Session {
+        Session {
+            peer: self.peer.clone(),
+            server_handle: self.server_handle.take(),
+            opponent_name: self.opponent_name.clone(),
+            room: self.room.clone(),
+            is_first: self.is_first,
+            chat_panel: self.chat_panel.take(),
+            series: self.series,
+            resigned: self.resigned.clone(),
+        }
+    }
+
+    // tells the opponent we leave on purpose, so they don't wait for us
+    // to come back.
+    pub async fn resign(&self) {
+        self.resigned.notify();
+
+        let mut peer = self.peer.lock().await;
+        if let Err(err) = peer.send_message(&protocol::Message::Resign).await {
+            println!("<Session> couldn't resign: {}", err);
+        }
+    }
+}
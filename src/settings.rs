@@ -0,0 +1,222 @@
+// board size and fleet composition agreed on before a match starts.
+
+use std::error::Error;
+use std::fmt;
+
+use super::engine::{ShipType, STANDARD_FLEET};
+
+pub const MIN_BOARD_LENGTH: i32 = 5;
+pub const MAX_BOARD_LENGTH: i32 = 20;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct MatchSettings {
+    pub width: i32,
+    pub height: i32,
+    // ship types in placement order, with how many of each.
+    pub fleet: Vec<(ShipType, usize)>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum SettingsError {
+    BoardSize(i32, i32),
+    EmptyFleet,
+    ShipTooLong(ShipType),
+    FleetTooLarge,
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SettingsError::BoardSize(w, h) => write!(
+                f,
+                "board must be between {0}x{0} and {1}x{1}, not {2}x{3}",
+                MIN_BOARD_LENGTH, MAX_BOARD_LENGTH, w, h
+            ),
+            SettingsError::EmptyFleet => write!(f, "fleet has no ships"),
+            SettingsError::ShipTooLong(ship_type) => {
+                write!(f, "{} doesn't fit on the board", ship_type.name())
+            }
+            SettingsError::FleetTooLarge => write!(f, "fleet covers too much of the board"),
+        }
+    }
+}
+
+impl Error for SettingsError {}
+
+impl Default for MatchSettings {
+    fn default() -> MatchSettings {
+        MatchSettings {
+            width: 10,
+            height: 10,
+            fleet: STANDARD_FLEET.iter().map(|t| (*t, 1)).collect(),
+        }
+    }
+}
+
+impl MatchSettings {
+    // the choices offered in the lobby, the first one is the default.
+    pub fn presets() -> Vec<MatchSettings> {
+        vec![
+            MatchSettings::default(),
+            MatchSettings {
+                width: 7,
+                height: 7,
+                fleet: vec![
+                    (ShipType::Battleship, 1),
+                    (ShipType::Destroyer, 1),
+                    (ShipType::PatrolBoat, 2),
+                ],
+            },
+            MatchSettings {
+                width: 14,
+                height: 12,
+                fleet: vec![
+                    (ShipType::Carrier, 1),
+                    (ShipType::Battleship, 2),
+                    (ShipType::Destroyer, 1),
+                    (ShipType::Submarine, 2),
+                    (ShipType::PatrolBoat, 3),
+                ],
+            },
+        ]
+    }
+
+    // every ship to place, in placement order. only call this on settings
+    // that passed 'validate', the counts may be anything before that.
+    pub fn ships(&self) -> Vec<ShipType> {
+        self.fleet
+            .iter()
+            .flat_map(|(ship_type, count)| std::iter::repeat_n(*ship_type, *count))
+            .collect()
+    }
+
+    // the cells the whole fleet covers, all of them hit means it's sunk.
+    // like 'ships', only for settings that passed 'validate'.
+    pub fn fleet_cells(&self) -> usize {
+        self.fleet
+            .iter()
+            .map(|(ship_type, count)| ship_type.size() * count)
+            .sum()
+    }
+
+    // checks the counts as they are, settings may come from a peer and
+    // must not be expanded into ships before they are known to be sane.
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        let sizes = MIN_BOARD_LENGTH..=MAX_BOARD_LENGTH;
+        if !sizes.contains(&self.width) || !sizes.contains(&self.height) {
+            return Err(SettingsError::BoardSize(self.width, self.height));
+        }
+
+        let mut fleet = self.fleet.iter().filter(|(_, count)| *count > 0);
+        if fleet.clone().next().is_none() {
+            return Err(SettingsError::EmptyFleet);
+        }
+
+        let longest_side = std::cmp::max(self.width, self.height) as usize;
+        if let Some((ship_type, _)) = fleet.clone().find(|(t, _)| t.size() > longest_side) {
+            return Err(SettingsError::ShipTooLong(*ship_type));
+        }
+
+        // leave enough water that a random layout is always found quickly.
+        let cells = fleet.try_fold(0usize, |cells, (ship_type, count)| {
+            ship_type
+                .size()
+                .checked_mul(*count)
+                .and_then(|ship_cells| cells.checked_add(ship_cells))
+        });
+        match cells.and_then(|cells| cells.checked_mul(3)) {
+            Some(cells) if cells <= (self.width * self.height) as usize => Ok(()),
+            _ => Err(SettingsError::FleetTooLarge),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(width: i32, height: i32, fleet: Vec<(ShipType, usize)>) -> MatchSettings {
+        MatchSettings {
+            width,
+            height,
+            fleet,
+        }
+    }
+
+    #[test]
+    fn presets_are_valid() {
+        for preset in MatchSettings::presets() {
+            assert_eq!(preset.validate(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn validate() {
+        let patrol = vec![(ShipType::PatrolBoat, 1)];
+
+        assert_eq!(
+            settings(4, 10, patrol.clone()).validate(),
+            Err(SettingsError::BoardSize(4, 10))
+        );
+        assert_eq!(
+            settings(10, 21, patrol.clone()).validate(),
+            Err(SettingsError::BoardSize(10, 21))
+        );
+        assert_eq!(
+            settings(10, 10, Vec::new()).validate(),
+            Err(SettingsError::EmptyFleet)
+        );
+        assert_eq!(
+            settings(10, 10, vec![(ShipType::Carrier, 0)]).validate(),
+            Err(SettingsError::EmptyFleet)
+        );
+        // a third of the board may be ships, no more.
+        assert_eq!(
+            settings(5, 6, vec![(ShipType::Carrier, 2)]).validate(),
+            Ok(())
+        );
+        assert_eq!(
+            settings(5, 5, vec![(ShipType::Carrier, 2)]).validate(),
+            Err(SettingsError::FleetTooLarge)
+        );
+    }
+
+    #[test]
+    fn huge_counts_dont_overflow() {
+        for count in [usize::MAX, usize::MAX / 2, usize::MAX / 3 + 1].iter() {
+            assert_eq!(
+                settings(20, 20, vec![(ShipType::PatrolBoat, *count)]).validate(),
+                Err(SettingsError::FleetTooLarge)
+            );
+        }
+        assert_eq!(
+            settings(
+                20,
+                20,
+                vec![
+                    (ShipType::PatrolBoat, usize::MAX / 2),
+                    (ShipType::PatrolBoat, 2)
+                ]
+            )
+            .validate(),
+            Err(SettingsError::FleetTooLarge)
+        );
+    }
+
+    #[test]
+    fn ships_and_cells() {
+        let preset = &MatchSettings::presets()[1];
+
+        assert_eq!(
+            preset.ships(),
+            vec![
+                ShipType::Battleship,
+                ShipType::Destroyer,
+                ShipType::PatrolBoat,
+                ShipType::PatrolBoat,
+            ]
+        );
+        assert_eq!(preset.fleet_cells(), 11);
+        assert_eq!(MatchSettings::default().fleet_cells(), 17);
+    }
+}
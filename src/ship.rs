@@ -1,74 +1,87 @@
-use sdl2::rect::Point;
+use rand::Rng;
 
-use super::config;
+use super::engine::Coord;
 
-#[derive(PartialEq, Eq, Copy, Clone)]
-pub enum ShipType {
-    Carrier,
-    Battleship,
-    Destroyer,
-    Submarine,
-    PatrolBoat,
-}
+pub use super::engine::ShipType;
 
 pub struct Ship {
-    pub body: Vec<Point>,
+    pub ship_type: ShipType,
+    pub body: Vec<Coord>,
+
+    board_width: i32,
+    board_height: i32,
 }
 
 impl Clone for Ship {
     fn clone(&self) -> Ship {
         Ship {
+            ship_type: self.ship_type,
             body: self.body.clone(),
+            board_width: self.board_width,
+            board_height: self.board_height,
         }
     }
 }
 
 impl Ship {
-    pub fn new(ship_type: ShipType) -> Ship {
-        let mut body: Vec<Point> = Vec::with_capacity(5);
-
-        match ship_type {
-            ShipType::Carrier => {
-                body.push(Point::new(0, 0));
-                body.push(Point::new(1, 0));
-                body.push(Point::new(2, 0));
-                body.push(Point::new(3, 0));
-                body.push(Point::new(4, 0));
-            }
-
-            ShipType::Battleship => {
-                body.push(Point::new(0, 0));
-                body.push(Point::new(1, 0));
-                body.push(Point::new(2, 0));
-                body.push(Point::new(3, 0));
-            }
+    pub fn new(ship_type: ShipType, board_width: i32, board_height: i32) -> Ship {
+        let body: Vec<Coord> = (0..ship_type.size() as i32)
+            .map(|x| Coord::new(x, 0))
+            .collect();
 
-            ShipType::Destroyer | ShipType::Submarine => {
-                body.push(Point::new(0, 0));
-                body.push(Point::new(1, 0));
-                body.push(Point::new(2, 0));
-            }
-
-            ShipType::PatrolBoat => {
-                body.push(Point::new(0, 0));
-                body.push(Point::new(1, 0));
-            }
+        Ship {
+            ship_type: ship_type,
+            body: body,
+            board_width: board_width,
+            board_height: board_height,
         }
+    }
+
+    // a ship anywhere it fits on the board, either way up with even odds.
+    // every start that fits is as likely as any other for that orientation.
+    pub fn random<R: Rng>(
+        ship_type: ShipType,
+        board_width: i32,
+        board_height: i32,
+        rng: &mut R,
+    ) -> Ship {
+        let mut ship = Ship::new(ship_type, board_width, board_height);
+        let size = ship_type.size() as i32;
+
+        let is_vertical = rng.gen_bool(0.5);
+        let (max_x, max_y) = if is_vertical {
+            (board_width - 1, board_height - size)
+        } else {
+            (board_width - size, board_height - 1)
+        };
+        let x = rng.gen_range(0, std::cmp::max(max_x, 0) + 1);
+        let y = rng.gen_range(0, std::cmp::max(max_y, 0) + 1);
+
+        // in the order 'rotate' leaves them, a vertical ship starts at the bottom.
+        ship.body = (0..size)
+            .map(|i| {
+                if is_vertical {
+                    Coord::new(x, y + size - 1 - i)
+                } else {
+                    Coord::new(x + i, y)
+                }
+            })
+            .collect();
+
+        ship
+    }
 
-        Ship { body: body }
+    pub fn cells(&self) -> &[Coord] {
+        &self.body
     }
 
     // is inside map borders?
-    fn is_valid_move(&self, dxy: &Point) -> bool {
+    fn is_valid_move(&self, dxy: &Coord) -> bool {
         for p in self.body.iter() {
             let new_x = p.x + dxy.x;
             let new_y = p.y + dxy.y;
 
-            if new_x < 0
-                || new_x >= config::BOARD_LENGTH as i32
-                || new_y < 0
-                || new_y >= config::BOARD_LENGTH as i32
-            {
+            if new_x < 0 || new_x >= self.board_width || new_y < 0 || new_y >= self.board_height {
                 return false;
             }
         }
@@ -76,14 +89,39 @@ impl Ship {
         return true;
     }
 
-    pub fn move_xy(&mut self, dxy: &Point) {
-        let new_point = |p: &Point| Point::new(p.x + dxy.x, p.y + dxy.y);
+    pub fn move_xy(&mut self, dxy: &Coord) {
+        let new_point = |p: &Coord| Coord::new(p.x + dxy.x, p.y + dxy.y);
 
         if self.is_valid_move(dxy) {
             self.body = self.body.iter().map(|p| new_point(p)).collect();
         }
     }
 
+    // moves the ship so its cell number 'part' lands on 'c', or as close
+    // to it as the borders allow.
+    pub fn move_to(&mut self, part: usize, c: &Coord) {
+        let part = match self.body.get(part) {
+            Some(p) => *p,
+            None => return,
+        };
+
+        let min_x = self.body.iter().map(|p| p.x).min().unwrap_or(0);
+        let max_x = self.body.iter().map(|p| p.x).max().unwrap_or(0);
+        let min_y = self.body.iter().map(|p| p.y).min().unwrap_or(0);
+        let max_y = self.body.iter().map(|p| p.y).max().unwrap_or(0);
+
+        let dx = std::cmp::min(
+            std::cmp::max(c.x - part.x, -min_x),
+            self.board_width - 1 - max_x,
+        );
+        let dy = std::cmp::min(
+            std::cmp::max(c.y - part.y, -min_y),
+            self.board_height - 1 - max_y,
+        );
+
+        self.move_xy(&Coord::new(dx, dy));
+    }
+
     fn is_valid_rotate(&self) -> bool {
         let mid_point = self.body.get(self.body.len() / 2).unwrap();
 
@@ -97,11 +135,7 @@ impl Ship {
             let new_x = aux_new_x + mid_point.x;
             let new_y = aux_new_y + mid_point.y;
 
-            if new_x < 0
-                || new_x >= config::BOARD_LENGTH as i32
-                || new_y < 0
-                || new_y >= config::BOARD_LENGTH as i32
-            {
+            if new_x < 0 || new_x >= self.board_width || new_y < 0 || new_y >= self.board_height {
                 return false;
             }
         }
@@ -114,7 +148,7 @@ impl Ship {
     pub fn rotate(&mut self) {
         let mid_point = self.body.get(self.body.len() / 2).unwrap();
 
-        let new_point_rotl = |p: &Point| {
+        let new_point_rotl = |p: &Coord| {
             let aux_x = p.x - mid_point.x;
             let aux_y = p.y - mid_point.y;
 
@@ -124,7 +158,7 @@ impl Ship {
             let new_x = aux_new_x + mid_point.x;
             let new_y = aux_new_y + mid_point.y;
 
-            return Point::new(new_x, new_y);
+            return Coord::new(new_x, new_y);
         };
 
         if self.is_valid_rotate() {
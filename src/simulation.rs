@@ -0,0 +1,139 @@
+// headless matches between computer players, for comparing strategies.
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use super::ai;
+use super::engine::{Board, Match, ShipType, Side, TargetBoard};
+
+// shots 'strategy' needs to sink every ship on 'board'.
+// 'None' if it ran out of cells first, which means the strategy is broken.
+pub fn shots_to_sink(
+    strategy: &mut dyn ai::AiStrategy,
+    mut board: Board,
+    rng: &mut StdRng,
+) -> Option<usize> {
+    let mut targets = TargetBoard::new(board.width(), board.height());
+    let mut shots = 0;
+
+    while !board.is_defeated() {
+        let shot = strategy.next_shot(&targets, rng)?;
+        let result = board.receive_shot(shot).ok()?;
+
+        targets.record(shot, result);
+        strategy.record(shot, result);
+        shots += 1;
+    }
+
+    Some(shots)
+}
+
+// plays one match to the end, 'first' shoots first.
+pub fn duel(first: &mut ai::Computer, second: &mut ai::Computer) -> Option<Side> {
+    let mut game = Match::new(first.board().clone(), second.board().clone());
+
+    while !game.is_over() {
+        let computer = match game.turn() {
+            Side::First => &mut *first,
+            Side::Second => &mut *second,
+        };
+
+        let shot = computer.next_shot()?;
+        let result = game.fire(game.turn(), shot).ok()?;
+        computer.record(shot, result);
+    }
+
+    game.winner()
+}
+
+pub struct Summary {
+    pub games: usize,
+    pub min_shots: usize,
+    pub max_shots: usize,
+    pub mean_shots: f64,
+}
+
+// runs 'difficulty' against 'games' random fleets seeded from 'seed'.
+pub fn benchmark(
+    difficulty: ai::Difficulty,
+    fleet: &[ShipType],
+    width: i32,
+    height: i32,
+    games: usize,
+    seed: u64,
+) -> Option<Summary> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut all_shots: Vec<usize> = Vec::with_capacity(games);
+
+    for _ in 0..games {
+        let board = ai::place_fleet(fleet, width, height, &mut rng)?;
+        let mut strategy = difficulty.strategy(fleet);
+        all_shots.push(shots_to_sink(strategy.as_mut(), board, &mut rng)?);
+    }
+
+    Some(Summary {
+        games,
+        min_shots: *all_shots.iter().min()?,
+        max_shots: *all_shots.iter().max()?,
+        mean_shots: all_shots.iter().sum::<usize>() as f64 / games as f64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::STANDARD_FLEET;
+    use rand::Rng;
+
+    #[test]
+    fn every_strategy_sinks_the_fleet() {
+        for difficulty in [
+            ai::Difficulty::Easy,
+            ai::Difficulty::Medium,
+            ai::Difficulty::Hard,
+        ]
+        .iter()
+        {
+            let summary = benchmark(*difficulty, &STANDARD_FLEET, 10, 10, 10, 7).unwrap();
+
+            assert_eq!(summary.games, 10);
+            assert!(summary.min_shots >= 17);
+            assert!(summary.max_shots <= 100);
+            assert!(summary.mean_shots >= summary.min_shots as f64);
+            assert!(summary.mean_shots <= summary.max_shots as f64);
+        }
+    }
+
+    #[test]
+    fn seeded_benchmarks_repeat() {
+        let run = || {
+            let summary = benchmark(ai::Difficulty::Hard, &STANDARD_FLEET, 10, 10, 5, 42).unwrap();
+            (summary.min_shots, summary.max_shots)
+        };
+
+        assert_eq!(run(), run());
+    }
+
+    #[test]
+    fn duel_has_a_winner() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut computer = |difficulty: ai::Difficulty| {
+            let board = ai::place_fleet(&STANDARD_FLEET, 10, 10, &mut rng).unwrap();
+            let seed = rng.gen();
+            ai::Computer::new(
+                board,
+                difficulty.strategy(&STANDARD_FLEET),
+                StdRng::seed_from_u64(seed),
+            )
+        };
+
+        let mut first = computer(ai::Difficulty::Hard);
+        let mut second = computer(ai::Difficulty::Easy);
+
+        let winner = match duel(&mut first, &mut second).unwrap() {
+            Side::First => first,
+            Side::Second => second,
+        };
+        assert_eq!(winner.targets().sunk().len(), STANDARD_FLEET.len());
+    }
+}
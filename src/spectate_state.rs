@@ -0,0 +1,295 @@
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+use sdl2::pixels::Color;
+use sdl2::rect::Point;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+use sdl2::EventPump;
+
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+use async_trait::async_trait;
+
+use super::audio;
+use super::chat;
+use super::choose_state;
+use super::commitment;
+use super::engine;
+use super::engine::Side;
+use super::geometry;
+use super::layout;
+use super::protocol;
+use super::room;
+use super::state;
+use super::text;
+use super::theme;
+
+// what a spectator has seen of a match, filled in by the watch task.
+struct View {
+    // the shots each side fired, the host's first.
+    targets: [engine::TargetBoard; 2],
+    // a shot waiting for the other side's answer.
+    pending: [Option<engine::Coord>; 2],
+    // only revealed once the match is over.
+    fleets: [Option<commitment::Fleet>; 2],
+    winner: Option<Side>,
+    error: Option<String>,
+}
+
+impl View {
+    fn apply(&mut self, side: Side, message: protocol::Message) {
+        let other = side.other();
+
+        match message {
+            protocol::Message::Shot(shot) => self.pending[side.index()] = Some(shot),
+
+            protocol::Message::ShotResult(result) => {
+                if let Some(shot) = self.pending[other.index()].take() {
+                    self.targets[other.index()].record(shot, result);
+                }
+            }
+
+            // the loser says so, or gives up.
+            protocol::Message::GameOver | protocol::Message::Resign => {
+                self.winner.get_or_insert(other);
+            }
+
+            protocol::Message::Reveal(_, fleet) => self.fleets[side.index()] = Some(fleet),
+
+            // joining late, the spectator never saw the shots a resume replays.
+            _ => {}
+        }
+    }
+}
+
+// a match played in a room, watched live. the spectator only reads what the
+// players send, so the fleets show up with the reveals after the match.
+pub struct SpectateState {
+    layout: layout::Layout,
+    room: room::Room,
+    // the host's shots on the left, the guest's on the right.
+    boards: (geometry::BoardGeometry, geometry::BoardGeometry),
+
+    // dropping the view stops the watch task.
+    view: Arc<Mutex<View>>,
+}
+
+impl SpectateState {
+    pub async fn new(address: String, room: room::Room) -> mini_redis::Result<SpectateState> {
+        let watch = chat::Watch::new(
+            address,
+            vec![
+                room::channel(&room.code, Side::First),
+                room::channel(&room.code, Side::Second),
+            ],
+        )
+        .await?;
+
+        let (width, height) = (room.settings.width, room.settings.height);
+        let view = Arc::new(Mutex::new(View {
+            targets: [
+                engine::TargetBoard::new(width, height),
+                engine::TargetBoard::new(width, height),
+            ],
+            pending: [None, None],
+            fleets: [None, None],
+            winner: None,
+            error: None,
+        }));
+
+        watch_match(watch, room.code.clone(), Arc::downgrade(&view));
+
+        let layout = layout::Layout::default();
+
+        Ok(SpectateState {
+            layout: layout,
+            boards: geometry::BoardGeometry::side_by_side(&layout, width, height),
+            room: room,
+            view: view,
+        })
+    }
+}
+
+fn watch_match(mut watch: chat::Watch, code: String, view: Weak<Mutex<View>>) {
+    tokio::spawn(async move {
+        let host_channel = room::watch_channel(&room::channel(&code, Side::First));
+
+        loop {
+            let received = watch.receive().await;
+
+            let view = match view.upgrade() {
+                Some(view) => view,
+                None => return,
+            };
+            let mut view = view.lock().await;
+
+            let msg = match received {
+                Ok(Some(msg)) => msg,
+                Ok(None) => {
+                    view.error = Some("the server closed the room".to_string());
+                    return;
+                }
+                Err(err) => {
+                    view.error = Some(format!("lost the room: {}", err));
+                    return;
+                }
+            };
+
+            let side = if msg.channel == host_channel {
+                Side::First
+            } else {
+                Side::Second
+            };
+
+            match protocol::Message::decode(&msg.content) {
+                Ok(message) => view.apply(side, message),
+                Err(err) => println!("<SpectateState> protocol error: {}", err),
+            }
+        }
+    });
+}
+
+#[async_trait(?Send)]
+impl state::State for SpectateState {
+    async fn handle_events(
+        &mut self,
+        event_pump: &mut EventPump,
+        next_state: &mut Option<state::NextState>,
+        _audio: &audio::Audio,
+    ) {
+        for event in event_pump.poll_iter() {
+            match event {
+                Event::Quit { .. } => {
+                    next_state.replace(state::NextState::Quit);
+                    return;
+                }
+
+                Event::KeyDown { keycode, .. } => match keycode {
+                    Some(Keycode::Escape) => {
+                        next_state.replace(state::NextState::Quit);
+                        return;
+                    }
+
+                    Some(Keycode::Backspace) => {
+                        next_state.replace(state::NextState::Update(Box::new(
+                            choose_state::ChooseState::new(),
+                        )));
+                        return;
+                    }
+
+                    _ => {
+                        if let Some(keycode) = keycode {
+                            println!("<SpectateState> unused key: {}", keycode);
+                        }
+                    }
+                },
+
+                _ => {}
+            }
+        }
+
+        next_state.replace(state::NextState::Continue);
+    }
+
+    async fn draw(
+        &mut self,
+        canvas: &mut Canvas<Window>,
+        text: &text::Text,
+        theme: &theme::Theme,
+        _dt: Duration,
+    ) {
+        let view = self.view.lock().await;
+
+        let (left, right) = self.boards;
+
+        let white = Color::RGBA(255, 255, 255, 255);
+        let center_x = self.layout.width / 2;
+
+        let headline = match view.winner {
+            Some(Side::First) => format!("room {}, {} won", self.room.code, self.room.host),
+            Some(Side::Second) => format!("room {}, the guest won", self.room.code),
+            None => format!("watching room {}", self.room.code),
+        };
+        text.draw_centered(
+            canvas,
+            text::Size::Large,
+            &headline,
+            Point::new(center_x, 40),
+            white,
+        );
+
+        for board in [left, right].iter() {
+            theme.draw_water(canvas, board);
+        }
+
+        // each board is the fleet the shots on it were fired at.
+        for (side, board, title) in [
+            (Side::First, left, format!("{}'s shots", self.room.host)),
+            (Side::Second, right, "the guest's shots".to_string()),
+        ]
+        .iter()
+        {
+            if let Some(fleet) = view.fleets[side.other().index()].as_ref() {
+                for (_, cells) in fleet.iter() {
+                    theme.draw_ship(canvas, board, cells);
+                }
+            }
+
+            let targets = &view.targets[side.index()];
+            theme.draw_markers(canvas, board, theme::Sprite::Hit, targets.hits());
+            theme.draw_markers(canvas, board, theme::Sprite::Miss, targets.misses());
+
+            text.draw_centered(
+                canvas,
+                text::Size::Normal,
+                title,
+                Point::new(board.body().center().x(), board.origin.y - 45),
+                white,
+            );
+            text.draw_board_labels(
+                canvas,
+                board.origin,
+                board.interval,
+                board.width,
+                board.height,
+                Color::RGBA(0, 255, 0, 255),
+            );
+        }
+
+        canvas.set_draw_color(Color::RGBA(0, 255, 0, 255));
+        for board in [left, right].iter() {
+            for (p1, p2) in board.lines().iter() {
+                canvas.draw_line(*p1, *p2).unwrap()
+            }
+        }
+
+        if let Some(error) = view.error.as_ref() {
+            text.draw_centered(
+                canvas,
+                text::Size::Small,
+                error,
+                Point::new(center_x, self.layout.height * 5 / 6),
+                Color::RGBA(255, 80, 80, 255),
+            );
+        }
+
+        text.draw_centered(
+            canvas,
+            text::Size::Small,
+            "BACKSPACE for the lobby, ESC to quit",
+            self.layout.bottom_center(15),
+            white,
+        );
+    }
+
+    fn relayout(&mut self, layout: &layout::Layout) {
+        self.layout = *layout;
+        self.boards = geometry::BoardGeometry::side_by_side(
+            layout,
+            self.room.settings.width,
+            self.room.settings.height,
+        );
+    }
+}
@@ -2,8 +2,15 @@ use sdl2::render::Canvas;
 use sdl2::video::Window;
 use sdl2::EventPump;
 
+use std::time::Duration;
+
 use async_trait::async_trait;
 
+use super::audio;
+use super::layout;
+use super::text;
+use super::theme;
+
 pub enum NextState {
     Update(Box<dyn State>),
     Continue,
@@ -16,6 +23,22 @@ pub trait State {
         &mut self,
         event_pump: &mut EventPump,
         next_state: &mut Option<NextState>,
+        audio: &audio::Audio,
+    );
+    async fn draw(
+        &mut self,
+        canvas: &mut Canvas<Window>,
+        text: &text::Text,
+        theme: &theme::Theme,
+        dt: Duration,
     );
-    async fn draw(&self, canvas: &mut Canvas<Window>);
+
+    // the window changed size, or this state was just made. boards, buttons
+    // and text go where 'layout' has room for them from now on.
+    fn relayout(&mut self, layout: &layout::Layout);
+
+    // whether every frame should be drawn, even with nothing going on.
+    fn is_animating(&self) -> bool {
+        false
+    }
 }
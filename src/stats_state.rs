@@ -7,149 +7,185 @@ use sdl2::render::Canvas;
 use sdl2::video::Window;
 use sdl2::EventPump;
 
+use std::sync::{Arc, Weak};
+use std::time::Duration;
 use std::vec::Vec;
+use tokio::sync::{Mutex, Notify};
 
 use async_trait::async_trait;
 
+use super::audio;
+use super::choose_state;
+use super::commitment;
 use super::config;
+use super::engine;
+use super::fleet_panel;
+use super::geometry;
+use super::initial_state;
+use super::layout;
+use super::peer;
+use super::protocol;
+use super::replay;
+use super::replay_state;
+use super::session;
 use super::state;
+use super::text;
+use super::theme;
+
+// how asking the opponent for another game went.
+enum Rematch {
+    NotAsked,
+    Waiting,
+    Agreed,
+    // the opponent went back to the lobby or the line broke.
+    Declined(String),
+}
 
 pub struct StatsState {
-    board_lines: Vec<(Point, Point)>,
-
-    opponent_hit_shots: Vec<Point>,
-    opponent_miss_shots: Vec<Point>,
-
-    my_hit_shots: Vec<Point>,
-    my_miss_shots: Vec<Point>,
+    layout: layout::Layout,
+    // my shots on the left, the opponent's on the right.
+    left: geometry::BoardGeometry,
+    right: geometry::BoardGeometry,
+
+    // my fleet and the opponent's shots at it.
+    my_board: engine::Board,
+    // my shots at the opponent's fleet.
+    my_targets: engine::TargetBoard,
+
+    replay: replay::Replay,
+    verdict: commitment::Verdict,
+
+    // this game already counts.
+    session: session::Session,
+    rematch: Arc<Mutex<Rematch>>,
+    rematch_button: choose_state::Button,
+    lobby_button: choose_state::Button,
 }
 
 impl StatsState {
     pub fn new(
-        opponent_hit_shots: Vec<Point>,
-        opponent_miss_shots: Vec<Point>,
-        my_hit_shots: Vec<Point>,
-        my_miss_shots: Vec<Point>,
+        my_board: engine::Board,
+        my_targets: engine::TargetBoard,
+        replay: replay::Replay,
+        verdict: commitment::Verdict,
+        mut session: session::Session,
     ) -> StatsState {
-        StatsState {
-            board_lines: StatsState::generate_board_lines(),
+        // a resigned opponent leaves our fleet afloat too.
+        let is_won = match verdict {
+            commitment::Verdict::Forfeit(_) | commitment::Verdict::Resigned => true,
+            _ => !my_board.is_defeated(),
+        };
+        session.series.record(is_won);
 
-            opponent_hit_shots: opponent_hit_shots,
-            opponent_miss_shots: opponent_miss_shots,
+        let layout = layout::Layout::default();
+        let (left, right) =
+            geometry::BoardGeometry::side_by_side(&layout, my_board.width(), my_board.height());
 
-            my_hit_shots: my_hit_shots,
-            my_miss_shots: my_miss_shots,
-        }
-    }
+        let mut stats_state = StatsState {
+            layout: layout,
+            left: left,
+            right: right,
 
-    fn generate_board_lines() -> Vec<(Point, Point)> {
-        let mut line_points: Vec<(Point, Point)> =
-            Vec::with_capacity(2 * ((config::BOARD_LENGTH + 1) * 2) as usize);
+            my_board: my_board,
+            my_targets: my_targets,
 
-        let min_wh: i32 = std::cmp::min(config::WINDOW_WIDTH as i32, config::WINDOW_HEIGHT as i32);
+            replay: replay,
+            verdict: verdict,
 
-        let offset: i32 = min_wh / 2;
-        let y_offset: i32 = offset / 2;
+            session: session,
+            rematch: Arc::new(Mutex::new(Rematch::NotAsked)),
 
-        let x_interval: i32 = offset / 10;
-        let y_interval: i32 = offset / 10;
+            rematch_button: choose_state::Button {
+                body: Rect::new(0, 0, 120, 40),
 
-        for i in 0..=config::BOARD_LENGTH {
-            // left vertical lines.
-            line_points.push((
-                Point::new(x_interval * i as i32 + x_interval, y_offset),
-                Point::new(
-                    x_interval * i as i32 + x_interval,
-                    config::WINDOW_HEIGHT as i32 - y_offset,
-                ),
-            ));
-
-            // left horizontal lines.
-            line_points.push((
-                Point::new(x_interval, y_interval * i as i32 + y_offset),
-                Point::new(offset + x_interval, y_interval * i as i32 + y_offset),
-            ));
-
-            // right vertical lines.
-            line_points.push((
-                Point::new(
-                    x_interval * i as i32 + config::WINDOW_WIDTH as i32 - offset - x_interval,
-                    y_offset,
-                ),
-                Point::new(
-                    x_interval * i as i32 + config::WINDOW_WIDTH as i32 - offset - x_interval,
-                    config::WINDOW_HEIGHT as i32 - y_offset,
-                ),
-            ));
-
-            // right horizontal lines.
-            line_points.push((
-                Point::new(
-                    config::WINDOW_WIDTH as i32 - offset - x_interval,
-                    y_interval * i as i32 + y_offset,
-                ),
-                Point::new(
-                    config::WINDOW_WIDTH as i32 - x_interval,
-                    y_interval * i as i32 + y_offset,
-                ),
-            ));
-        }
+                color: Color::RGBA(0, 255, 0, 255),
 
-        line_points
-    }
+                text: String::from("rematch"),
+            },
 
-    fn draw_shots_left(&self, canvas: &mut Canvas<Window>, color: Color, shots: &Vec<Point>) {
-        let min_wh: i32 = std::cmp::min(config::WINDOW_WIDTH as i32, config::WINDOW_HEIGHT as i32);
+            lobby_button: choose_state::Button {
+                body: Rect::new(0, 0, 120, 40),
 
-        let offset: i32 = min_wh / 2;
-        let y_offset: i32 = offset / 2;
+                color: Color::RGBA(150, 150, 150, 255),
 
-        let x_interval: i32 = offset / 10;
-        let y_interval: i32 = offset / 10;
+                text: String::from("lobby"),
+            },
+        };
+        stats_state.place_buttons();
 
-        canvas.set_draw_color(color);
+        stats_state
+    }
 
-        let mut cache: Vec<Rect> = Vec::new();
+    // the buttons under the series score, between the boards.
+    fn place_buttons(&mut self) {
+        let center = self.layout.center();
+        self.rematch_button.body.center_on(center.offset(0, -30));
+        self.lobby_button.body.center_on(center.offset(0, 30));
+    }
 
-        for point in shots.iter() {
-            let rect = Rect::new(
-                point.x * x_interval + x_interval,
-                point.y * y_interval + y_offset,
-                x_interval as u32,
-                y_interval as u32,
-            );
-            cache.push(rect);
+    async fn ask_rematch(&self) {
+        let mut rematch = self.rematch.lock().await;
+        if !matches!(*rematch, Rematch::NotAsked) {
+            return;
         }
+        *rematch = Rematch::Waiting;
 
-        canvas.fill_rects(&cache[..]).unwrap();
-        canvas.draw_rects(&cache[..]).unwrap();
-    }
+        let peer = self.session.peer.clone();
+        let rematch = Arc::downgrade(&self.rematch);
+        let resigned = self.session.resigned.clone();
 
-    fn draw_shots_right(&self, canvas: &mut Canvas<Window>, color: Color, shots: &Vec<Point>) {
-        let min_wh: i32 = std::cmp::min(config::WINDOW_WIDTH as i32, config::WINDOW_HEIGHT as i32);
+        tokio::spawn(async move {
+            let mut peer = peer.lock().await;
+            let result = negotiate(&mut **peer, &rematch, &resigned).await;
 
-        let offset: i32 = min_wh / 2;
-        let y_offset: i32 = offset / 2;
+            if let Some(rematch) = rematch.upgrade() {
+                *rematch.lock().await = result;
+            }
+        });
+    }
 
-        let x_interval: i32 = offset / 10;
-        let y_interval: i32 = offset / 10;
+    // the series score and what comes next, between the boards.
+    async fn draw_series(&self, canvas: &mut Canvas<Window>, text: &text::Text) {
+        let white = Color::RGBA(255, 255, 255, 255);
+        let center = self.layout.center();
+        let series = &self.session.series;
 
-        canvas.set_draw_color(color);
+        text.draw_centered(
+            canvas,
+            text::Size::Normal,
+            &format!("series {}-{}", series.wins, series.losses),
+            center.offset(0, -110),
+            white,
+        );
 
-        let mut cache: Vec<Rect> = Vec::new();
+        let outcome = match series.is_decided() {
+            true if series.wins > series.losses => "you won it".to_string(),
+            true => "you lost it".to_string(),
+            false => format!("best of {}", series.best_of),
+        };
+        text.draw_centered(
+            canvas,
+            text::Size::Small,
+            &outcome,
+            center.offset(0, -85),
+            white,
+        );
 
-        for point in shots.iter() {
-            let rect = Rect::new(
-                point.x * x_interval + config::WINDOW_WIDTH as i32 - offset - x_interval,
-                point.y * y_interval + y_offset,
-                x_interval as u32,
-                y_interval as u32,
-            );
-            cache.push(rect);
-        }
+        self.rematch_button.draw(canvas, text);
+        self.lobby_button.draw(canvas, text);
 
-        canvas.fill_rects(&cache[..]).unwrap();
-        canvas.draw_rects(&cache[..]).unwrap();
+        let (status, color) = match &*self.rematch.lock().await {
+            Rematch::NotAsked | Rematch::Agreed => return,
+            Rematch::Waiting => ("waiting for them".to_string(), white),
+            Rematch::Declined(reason) => (reason.clone(), Color::RGBA(255, 80, 80, 255)),
+        };
+        text.draw_centered(
+            canvas,
+            text::Size::Small,
+            &status,
+            center.offset(0, 70),
+            color,
+        );
     }
 }
 
@@ -159,25 +195,76 @@ impl state::State for StatsState {
         &mut self,
         event_pump: &mut EventPump,
         next_state: &mut Option<state::NextState>,
+        _audio: &audio::Audio,
     ) {
+        if let Rematch::Agreed = *self.rematch.lock().await {
+            next_state.replace(state::NextState::Update(Box::new(
+                initial_state::InitialState::rematch(
+                    self.session.hand_over(),
+                    self.replay.settings.clone(),
+                ),
+            )));
+            return;
+        }
+
         for event in event_pump.poll_iter() {
             match event {
                 Event::Quit { .. } => {
+                    self.session.resign().await;
                     next_state.replace(state::NextState::Quit);
                     return;
                 }
 
                 Event::KeyDown { keycode, .. } => match keycode {
                     Some(Keycode::Escape) => {
+                        self.session.resign().await;
                         next_state.replace(state::NextState::Quit);
                         return;
                     }
 
+                    // watch the match we just played.
+                    Some(Keycode::R) => {
+                        self.session.resign().await;
+                        next_state.replace(state::NextState::Update(Box::new(
+                            replay_state::ReplayState::new(self.replay.clone()),
+                        )));
+                        return;
+                    }
+
+                    Some(Keycode::Return) => self.ask_rematch().await,
+
+                    Some(Keycode::Backspace) => {
+                        self.session.resign().await;
+                        next_state.replace(state::NextState::Update(Box::new(
+                            choose_state::ChooseState::new(),
+                        )));
+                        return;
+                    }
+
                     _ => {
-                        println!("<InitialState> unused key: {}", keycode.unwrap());
+                        if let Some(keycode) = keycode {
+                            println!("<StatsState> unused key: {}", keycode);
+                        }
                     }
                 },
 
+                Event::MouseButtonDown {
+                    mouse_btn: sdl2::mouse::MouseButton::Left,
+                    x,
+                    y,
+                    ..
+                } => {
+                    if self.rematch_button.is_click(x, y) {
+                        self.ask_rematch().await;
+                    } else if self.lobby_button.is_click(x, y) {
+                        self.session.resign().await;
+                        next_state.replace(state::NextState::Update(Box::new(
+                            choose_state::ChooseState::new(),
+                        )));
+                        return;
+                    }
+                }
+
                 _ => {}
             }
         }
@@ -185,25 +272,249 @@ impl state::State for StatsState {
         next_state.replace(state::NextState::Continue);
     }
 
-    async fn draw(&self, canvas: &mut Canvas<Window>) {
+    async fn draw(
+        &mut self,
+        canvas: &mut Canvas<Window>,
+        text: &text::Text,
+        theme: &theme::Theme,
+        _dt: Duration,
+    ) {
         // draw board lines.
-        canvas.set_draw_color(Color::RGBA(0, 255, 0, 255));
-        for (p1, p2) in self.board_lines.iter() {
-            canvas.draw_line(*p1, *p2).unwrap()
+        for board in [self.left, self.right].iter() {
+            theme.draw_water(canvas, board);
+
+            canvas.set_draw_color(Color::RGBA(0, 255, 0, 255));
+            for (p1, p2) in board.lines().iter() {
+                canvas.draw_line(*p1, *p2).unwrap()
+            }
+        }
+
+        let white = Color::RGBA(255, 255, 255, 255);
+        let center_x = self.layout.width / 2;
+
+        let headline = match &self.verdict {
+            commitment::Verdict::Forfeit(_) => "you won, the opponent forfeits",
+            commitment::Verdict::Resigned => "you won, the opponent resigned",
+            _ if self.my_board.is_defeated() => "you lost",
+            _ => "you won",
+        };
+        text.draw_centered(
+            canvas,
+            text::Size::Large,
+            headline,
+            Point::new(center_x, 40),
+            white,
+        );
+
+        let (flag, flag_color) = match &self.verdict {
+            commitment::Verdict::Verified => (
+                "the opponent's answers match their fleet".to_string(),
+                Color::RGBA(0, 255, 0, 255),
+            ),
+            commitment::Verdict::Unverified => (
+                "the opponent's fleet wasn't revealed".to_string(),
+                Color::RGBA(255, 255, 255, 150),
+            ),
+            commitment::Verdict::Resigned => (
+                "the opponent left the match".to_string(),
+                Color::RGBA(255, 255, 255, 150),
+            ),
+            commitment::Verdict::Forfeit(cheat) => {
+                (format!("flagged: {}", cheat), Color::RGBA(255, 80, 80, 255))
+            }
+        };
+        text.draw_centered(
+            canvas,
+            text::Size::Small,
+            &flag,
+            Point::new(center_x, 72),
+            flag_color,
+        );
+
+        let my_sunk = self.my_targets.sunk().len();
+        let their_sunk = self
+            .my_board
+            .ships()
+            .iter()
+            .filter(|ship| ship.is_sunk())
+            .count();
+
+        for (board, title, hits, misses, sunk) in [
+            (
+                self.left,
+                "your shots",
+                self.my_targets.hits().len(),
+                self.my_targets.misses().len(),
+                my_sunk,
+            ),
+            (
+                self.right,
+                "opponent's shots",
+                self.my_board.hits().len(),
+                self.my_board.misses().len(),
+                their_sunk,
+            ),
+        ]
+        .iter()
+        {
+            let body = board.body();
+            text.draw_centered(
+                canvas,
+                text::Size::Normal,
+                title,
+                Point::new(body.center().x(), body.top() - 70),
+                white,
+            );
+            text.draw_centered(
+                canvas,
+                text::Size::Small,
+                &summary(*hits, *misses, *sunk),
+                Point::new(body.center().x(), body.top() - 45),
+                white,
+            );
+
+            text.draw_board_labels(
+                canvas,
+                board.origin,
+                board.interval,
+                board.width,
+                board.height,
+                Color::RGBA(0, 255, 0, 255),
+            );
         }
 
-        self.draw_shots_left(canvas, Color::RGBA(0, 255, 0, 200), &self.my_hit_shots);
-        self.draw_shots_left(canvas, Color::RGBA(0, 0, 255, 200), &self.my_miss_shots);
+        text.draw_centered(
+            canvas,
+            text::Size::Small,
+            "RETURN for a rematch, R to watch the replay, BACKSPACE for the lobby, ESC to quit",
+            self.layout.bottom_center(15),
+            white,
+        );
 
-        self.draw_shots_right(
+        theme.draw_markers(
             canvas,
+            &self.left,
+            theme::Sprite::Hit,
+            self.my_targets.hits(),
+        );
+        theme.draw_markers(
+            canvas,
+            &self.left,
+            theme::Sprite::Miss,
+            self.my_targets.misses(),
+        );
+
+        // my fleet under the opponent's shots.
+        for ship in self.my_board.ships().iter() {
+            theme.draw_ship(canvas, &self.right, ship.cells());
+        }
+        theme.draw_markers(
+            canvas,
+            &self.right,
+            theme::Sprite::Hit,
+            self.my_board.hits(),
+        );
+        theme.draw_markers(
+            canvas,
+            &self.right,
+            theme::Sprite::Miss,
+            self.my_board.misses(),
+        );
+
+        self.draw_series(canvas, text).await;
+
+        // fleet status under each board.
+        let panel_y = self.left.body().bottom() + 10;
+
+        let fleet: Vec<engine::ShipType> = self
+            .my_board
+            .ships()
+            .iter()
+            .map(|ship| ship.ship_type())
+            .collect();
+        let is_sunk: Vec<bool> = self
+            .my_board
+            .ships()
+            .iter()
+            .map(|ship| ship.is_sunk())
+            .collect();
+
+        fleet_panel::draw(
+            canvas,
+            self.left.origin.x,
+            panel_y,
+            &fleet,
+            &fleet_panel::sunk_flags(&fleet, self.my_targets.sunk()),
             Color::RGBA(255, 0, 0, 200),
-            &self.opponent_hit_shots,
         );
-        self.draw_shots_right(
+        fleet_panel::draw(
             canvas,
-            Color::RGBA(0, 0, 255, 200),
-            &self.opponent_miss_shots,
+            self.right.origin.x,
+            panel_y,
+            &fleet,
+            &is_sunk,
+            Color::RGBA(0, 255, 0, 200),
         );
     }
+
+    fn relayout(&mut self, layout: &layout::Layout) {
+        self.layout = *layout;
+
+        let (left, right) =
+            geometry::BoardGeometry::side_by_side(layout, self.left.width, self.left.height);
+        self.left = left;
+        self.right = right;
+        self.place_buttons();
+    }
+}
+
+// negotiates another game: sends our 'Rematch' and waits for theirs, which
+// may have come already. an opponent gone quiet went back to the lobby.
+async fn negotiate(
+    peer: &mut dyn peer::Peer,
+    rematch: &Weak<Mutex<Rematch>>,
+    resigned: &Notify,
+) -> Rematch {
+    if let Err(err) = peer.send_message(&protocol::Message::Rematch).await {
+        return Rematch::Declined(format!("lost the opponent: {}", err));
+    }
+
+    loop {
+        let received = tokio::select! {
+            received = tokio::time::timeout(config::DISCONNECT_TIMEOUT, peer.receive()) => received,
+            // we're leaving, the peer is needed to say so.
+            _ = resigned.notified() => return Rematch::NotAsked,
+        };
+
+        let frame = match received {
+            Ok(Ok(Some(frame))) => frame,
+            Ok(Ok(None)) | Err(_) => return Rematch::Declined("the opponent left".to_string()),
+            Ok(Err(err)) => return Rematch::Declined(format!("lost the opponent: {}", err)),
+        };
+
+        // we went back to the lobby ourselves.
+        if rematch.strong_count() == 0 {
+            return Rematch::NotAsked;
+        }
+
+        match protocol::Message::decode(&frame) {
+            Ok(protocol::Message::Rematch) => return Rematch::Agreed,
+            Ok(protocol::Message::Resign) => {
+                return Rematch::Declined("the opponent went back to the lobby".to_string())
+            }
+            // pings and leftovers of the match that just ended.
+            _ => continue,
+        }
+    }
+}
+
+// "<shots> shots, <hits> hits (<accuracy>%), <sunk> sunk"
+fn summary(hits: usize, misses: usize, sunk: usize) -> String {
+    let shots = hits + misses;
+    let accuracy = (hits * 100).checked_div(shots).unwrap_or(0);
+
+    format!(
+        "{} shots, {} hits ({}%), {} sunk",
+        shots, hits, accuracy, sunk
+    )
 }
@@ -0,0 +1,176 @@
+use sdl2::pixels::Color;
+use sdl2::rect::Point;
+use sdl2::rect::Rect;
+use sdl2::render::{Canvas, Texture, TextureCreator};
+use sdl2::rwops::RWops;
+use sdl2::ttf::{Font, Sdl2TtfContext};
+use sdl2::video::{Window, WindowContext};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+// shipped with the game so every machine draws the same glyphs,
+// see 'assets/fonts/LICENSE'.
+const FONT: &[u8] = include_bytes!("../assets/fonts/DejaVuSans.ttf");
+
+// rendered labels kept at most, chat lines and typed text keep adding new ones.
+const MAX_CACHED: usize = 256;
+
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum Size {
+    Small,
+    Normal,
+    Large,
+}
+
+pub struct Text {
+    small: Font<'static, 'static>,
+    normal: Font<'static, 'static>,
+    large: Font<'static, 'static>,
+    creator: &'static TextureCreator<WindowContext>,
+    // most labels are drawn every frame, they are only rendered once.
+    cache: RefCell<HashMap<(Size, String, Color), Texture<'static>>>,
+}
+
+impl Text {
+    pub fn new(canvas: &Canvas<Window>) -> Result<Text, String> {
+        // fonts borrow the ttf context, which has to live as long as the game.
+        let ttf: &'static Sdl2TtfContext =
+            Box::leak(Box::new(sdl2::ttf::init().map_err(|err| err.to_string())?));
+
+        let load = |point_size| ttf.load_font_from_rwops(RWops::from_bytes(FONT)?, point_size);
+
+        Ok(Text {
+            small: load(12)?,
+            normal: load(16)?,
+            large: load(28)?,
+            creator: Box::leak(Box::new(canvas.texture_creator())),
+            cache: RefCell::new(HashMap::new()),
+        })
+    }
+
+    fn font(&self, size: Size) -> &Font<'static, 'static> {
+        match size {
+            Size::Small => &self.small,
+            Size::Normal => &self.normal,
+            Size::Large => &self.large,
+        }
+    }
+
+    pub fn size_of(&self, size: Size, text: &str) -> (u32, u32) {
+        self.font(size).size_of(text).unwrap_or((0, 0))
+    }
+
+    // 'x' and 'y' are the top left corner of the text.
+    pub fn draw(
+        &self,
+        canvas: &mut Canvas<Window>,
+        size: Size,
+        text: &str,
+        x: i32,
+        y: i32,
+        color: Color,
+    ) {
+        if text.is_empty() {
+            return;
+        }
+
+        let key = (size, text.to_string(), color);
+        let mut cache = self.cache.borrow_mut();
+
+        if !cache.contains_key(&key) {
+            let surface = match self.font(size).render(text).blended(color) {
+                Ok(surface) => surface,
+                Err(err) => {
+                    println!("<Text> couldn't render '{}': {}", text, err);
+                    return;
+                }
+            };
+
+            let texture = match self.creator.create_texture_from_surface(&surface) {
+                Ok(texture) => texture,
+                Err(err) => {
+                    println!("<Text> couldn't render '{}': {}", text, err);
+                    return;
+                }
+            };
+
+            if cache.len() >= MAX_CACHED {
+                cache.clear();
+            }
+            cache.insert(key.clone(), texture);
+        }
+
+        let texture = &cache[&key];
+        let query = texture.query();
+        canvas
+            .copy(
+                texture,
+                None,
+                Some(Rect::new(x, y, query.width, query.height)),
+            )
+            .unwrap();
+    }
+
+    pub fn draw_centered(
+        &self,
+        canvas: &mut Canvas<Window>,
+        size: Size,
+        text: &str,
+        center: Point,
+        color: Color,
+    ) {
+        let (w, h) = self.size_of(size, text);
+        self.draw(
+            canvas,
+            size,
+            text,
+            center.x - w as i32 / 2,
+            center.y - h as i32 / 2,
+            color,
+        );
+    }
+
+    // column letters above and row numbers left of a board whose top left
+    // corner is 'origin'.
+    pub fn draw_board_labels(
+        &self,
+        canvas: &mut Canvas<Window>,
+        origin: Point,
+        interval: i32,
+        width: i32,
+        height: i32,
+        color: Color,
+    ) {
+        let (x, y) = (origin.x, origin.y);
+        let size = if interval < 24 {
+            Size::Small
+        } else {
+            Size::Normal
+        };
+
+        for i in 0..width {
+            let label = ((b'A' + i as u8) as char).to_string();
+            self.draw_centered(
+                canvas,
+                size,
+                &label,
+                Point::new(x + interval * i + interval / 2, y - 12),
+                color,
+            );
+        }
+
+        for i in 0..height {
+            let label = (i + 1).to_string();
+            let (w, h) = self.size_of(size, &label);
+            self.draw(
+                canvas,
+                size,
+                &label,
+                x - w as i32 - 6,
+                y + interval * i + (interval - h as i32) / 2,
+                color,
+            );
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+
+use super::text;
+
+// longest value a field takes, more than enough for "host:port".
+const MAX_LENGTH: usize = 64;
+
+// a one line input typed into through sdl text input events.
+pub struct TextField {
+    pub body: Rect,
+    pub value: String,
+    pub is_focused: bool,
+    // addresses and codes are one word, chat lines aren't.
+    pub allows_spaces: bool,
+}
+
+impl TextField {
+    pub fn new(body: Rect, value: String) -> TextField {
+        TextField {
+            body: body,
+            value: value,
+            is_focused: false,
+            allows_spaces: false,
+        }
+    }
+
+    pub fn is_click(&self, x: i32, y: i32) -> bool {
+        self.body.contains_point((x, y))
+    }
+
+    // returns whether the event was meant for this field.
+    pub fn handle_event(&mut self, event: &Event) -> bool {
+        if !self.is_focused {
+            return false;
+        }
+
+        match event {
+            Event::TextInput { text, .. } => {
+                let allows_spaces = self.allows_spaces;
+                for c in text
+                    .chars()
+                    .filter(|c| !c.is_whitespace() || (allows_spaces && *c == ' '))
+                {
+                    if self.value.len() < MAX_LENGTH {
+                        self.value.push(c);
+                    }
+                }
+                return true;
+            }
+
+            Event::KeyDown {
+                keycode: Some(Keycode::Backspace),
+                ..
+            } => {
+                self.value.pop();
+                return true;
+            }
+
+            // letters also arrive as text input, don't let them act as shortcuts.
+            Event::KeyDown {
+                keycode: Some(Keycode::Escape),
+                ..
+            }
+            | Event::KeyDown {
+                keycode: Some(Keycode::Return),
+                ..
+            } => return false,
+
+            Event::KeyDown { .. } => return true,
+
+            _ => return false,
+        }
+    }
+
+    pub fn draw(&self, canvas: &mut Canvas<Window>, text: &text::Text) {
+        canvas.set_draw_color(Color::RGBA(30, 30, 30, 255));
+        canvas.fill_rect(self.body).unwrap();
+
+        if self.is_focused {
+            canvas.set_draw_color(Color::RGBA(255, 255, 255, 255));
+        } else {
+            canvas.set_draw_color(Color::RGBA(120, 120, 120, 255));
+        }
+        canvas.draw_rect(self.body).unwrap();
+
+        let value = if self.is_focused {
+            format!("{}|", self.value)
+        } else {
+            self.value.clone()
+        };
+
+        let (_, h) = text.size_of(text::Size::Normal, &value);
+        text.draw(
+            canvas,
+            text::Size::Normal,
+            &value,
+            self.body.x() + 8,
+            self.body.y() + (self.body.height() as i32 - h as i32) / 2,
+            Color::RGBA(255, 255, 255, 255),
+        );
+    }
+}
@@ -0,0 +1,191 @@
+// sprites for the water, ships and shots. the default theme is embedded,
+// see 'assets/themes/default', a theme in 'config::THEME_DIR' replaces
+// whichever sprites it has.
+
+use sdl2::image::LoadTexture;
+use sdl2::rect::Rect;
+use sdl2::render::{Canvas, Texture, TextureCreator};
+use sdl2::video::{Window, WindowContext};
+
+use std::path::Path;
+
+use super::config;
+use super::engine;
+use super::geometry;
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Sprite {
+    Water,
+    // ship segments, horizontal ones point left.
+    Bow,
+    Middle,
+    Stern,
+    // pointing up, turned from the horizontal ones when a theme has none.
+    BowVertical,
+    MiddleVertical,
+    SternVertical,
+    Hit,
+    Miss,
+}
+
+const SPRITES: [Sprite; 9] = [
+    Sprite::Water,
+    Sprite::Bow,
+    Sprite::Middle,
+    Sprite::Stern,
+    Sprite::BowVertical,
+    Sprite::MiddleVertical,
+    Sprite::SternVertical,
+    Sprite::Hit,
+    Sprite::Miss,
+];
+
+impl Sprite {
+    fn file_name(&self) -> &'static str {
+        match self {
+            Sprite::Water => "water.png",
+            Sprite::Bow => "bow.png",
+            Sprite::Middle => "middle.png",
+            Sprite::Stern => "stern.png",
+            Sprite::BowVertical => "bow_vertical.png",
+            Sprite::MiddleVertical => "middle_vertical.png",
+            Sprite::SternVertical => "stern_vertical.png",
+            Sprite::Hit => "hit.png",
+            Sprite::Miss => "miss.png",
+        }
+    }
+
+    fn embedded(&self) -> Option<&'static [u8]> {
+        match self {
+            Sprite::Water => Some(include_bytes!("../assets/themes/default/water.png")),
+            Sprite::Bow => Some(include_bytes!("../assets/themes/default/bow.png")),
+            Sprite::Middle => Some(include_bytes!("../assets/themes/default/middle.png")),
+            Sprite::Stern => Some(include_bytes!("../assets/themes/default/stern.png")),
+            Sprite::Hit => Some(include_bytes!("../assets/themes/default/hit.png")),
+            Sprite::Miss => Some(include_bytes!("../assets/themes/default/miss.png")),
+            _ => None,
+        }
+    }
+
+    // the horizontal segment a vertical one is turned from.
+    fn horizontal(&self) -> Option<Sprite> {
+        match self {
+            Sprite::BowVertical => Some(Sprite::Bow),
+            Sprite::MiddleVertical => Some(Sprite::Middle),
+            Sprite::SternVertical => Some(Sprite::Stern),
+            _ => None,
+        }
+    }
+}
+
+pub struct Theme {
+    sprites: Vec<(Sprite, Texture<'static>)>,
+}
+
+impl Theme {
+    // 'name' is a directory in 'config::THEME_DIR', a missing one leaves
+    // the default theme as it is.
+    pub fn load(canvas: &Canvas<Window>, name: &str) -> Result<Theme, String> {
+        // textures borrow their creator, which has to live as long as the game,
+        // and so does the image context.
+        Box::leak(Box::new(sdl2::image::init(sdl2::image::InitFlag::PNG)?));
+        let creator: &'static TextureCreator<WindowContext> =
+            Box::leak(Box::new(canvas.texture_creator()));
+
+        let dir = Path::new(config::THEME_DIR).join(name);
+
+        let mut sprites: Vec<(Sprite, Texture<'static>)> = Vec::with_capacity(SPRITES.len());
+        for sprite in SPRITES.iter() {
+            let path = dir.join(sprite.file_name());
+
+            // a broken sprite of a user theme falls back to the default one.
+            let texture = match creator.load_texture(&path) {
+                Ok(texture) => Some(texture),
+                Err(_) if !path.is_file() => None,
+                Err(err) => {
+                    println!("<Theme> couldn't load {}: {}", path.display(), err);
+                    None
+                }
+            };
+
+            let texture = match (texture, sprite.embedded()) {
+                (Some(texture), _) => texture,
+                (None, Some(png)) => creator.load_texture_bytes(png)?,
+                (None, None) => continue,
+            };
+
+            sprites.push((*sprite, texture));
+        }
+
+        Ok(Theme { sprites: sprites })
+    }
+
+    fn texture(&self, sprite: Sprite) -> Option<&Texture<'static>> {
+        self.sprites
+            .iter()
+            .find(|(s, _)| *s == sprite)
+            .map(|(_, texture)| texture)
+    }
+
+    pub fn draw(&self, canvas: &mut Canvas<Window>, sprite: Sprite, rect: Rect) {
+        if let Some(texture) = self.texture(sprite) {
+            canvas.copy(texture, None, rect).unwrap();
+        } else if let Some(texture) = sprite.horizontal().and_then(|s| self.texture(s)) {
+            // a quarter turn clockwise brings the bow from the left to the top.
+            canvas
+                .copy_ex(texture, None, rect, 90.0, None, false, false)
+                .unwrap();
+        }
+    }
+
+    pub fn draw_water(&self, canvas: &mut Canvas<Window>, board: &geometry::BoardGeometry) {
+        for y in 0..board.height {
+            for x in 0..board.width {
+                self.draw(
+                    canvas,
+                    Sprite::Water,
+                    board.cell_rect(engine::Coord::new(x, y)),
+                );
+            }
+        }
+    }
+
+    // 'cells' in a row or a column, bow first.
+    pub fn draw_ship(
+        &self,
+        canvas: &mut Canvas<Window>,
+        board: &geometry::BoardGeometry,
+        cells: &[engine::Coord],
+    ) {
+        let is_vertical = cells.windows(2).all(|pair| pair[0].x == pair[1].x);
+
+        let mut cells = cells.to_vec();
+        cells.sort_by_key(|c| if is_vertical { c.y } else { c.x });
+
+        for (i, c) in cells.iter().enumerate() {
+            let sprite = match (i, is_vertical) {
+                _ if cells.len() == 1 => Sprite::Middle,
+                (0, false) => Sprite::Bow,
+                (i, false) if i + 1 == cells.len() => Sprite::Stern,
+                (_, false) => Sprite::Middle,
+                (0, true) => Sprite::BowVertical,
+                (i, true) if i + 1 == cells.len() => Sprite::SternVertical,
+                (_, true) => Sprite::MiddleVertical,
+            };
+
+            self.draw(canvas, sprite, board.cell_rect(*c));
+        }
+    }
+
+    pub fn draw_markers(
+        &self,
+        canvas: &mut Canvas<Window>,
+        board: &geometry::BoardGeometry,
+        sprite: Sprite,
+        cells: &[engine::Coord],
+    ) {
+        for c in cells.iter() {
+            self.draw(canvas, sprite, board.cell_rect(*c));
+        }
+    }
+}
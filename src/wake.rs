@@ -0,0 +1,27 @@
+// lets the network tasks tell an idle game loop that a message came in,
+// so the screen catches up with it, see 'Game::wait_for_change'. they push
+// an SDL event the loop is blocked on, like a key press would.
+
+use sdl2::event::EventSender;
+use sdl2::EventSubsystem;
+use std::sync::Mutex;
+
+// the event that is pushed, there is nothing in it.
+pub struct Wake;
+
+// 'None' until the game registered the event.
+static SENDER: Mutex<Option<EventSender>> = Mutex::new(None);
+
+pub fn register(events: &EventSubsystem) -> Result<(), String> {
+    events.register_custom_event::<Wake>()?;
+    *SENDER.lock().unwrap() = Some(events.event_sender());
+    Ok(())
+}
+
+pub fn wake() {
+    if let Some(sender) = SENDER.lock().unwrap().as_ref() {
+        if let Err(err) = sender.push_custom_event(Wake) {
+            println!("<Wake> couldn't wake the game: {}", err);
+        }
+    }
+}